@@ -26,7 +26,8 @@ let stakingMintObject;
 let stakingTokenPubkey;
 let stakingMintPubkey = new anchor.web3.PublicKey('EnMRdXxzohDn3PJCdvVJzBMD2FzSQegwVm87y44Pbai5');
 let mintRewards = new anchor.web3.PublicKey('HuMJHQL3UbiECz8ZB7aAWeEG9Nn3WrHmkgwgNpkWYL77');
-let poolKeypair, rewardsMintObject;
+let poolIndex = parseInt(process.env.POOL_INDEX || '0');
+let poolPubkey, poolBump, rewardsMintObject;
 
 const initializeMints = async () => {
   console.log("Program ID: ", programID.toString());
@@ -35,8 +36,12 @@ const initializeMints = async () => {
   stakingMintObject = new Token(provider.connection, stakingMintPubkey, TOKEN_PROGRAM_ID, provider.wallet.payer);
   rewardsMintObject = new Token(provider.connection, mintRewards, TOKEN_PROGRAM_ID, provider.wallet.payer);
   
-  const poolRawData = fs.readFileSync('json/pool.json');
-  poolKeypair = anchor.web3.Keypair.fromSecretKey(new Uint8Array(JSON.parse(poolRawData)));
+  let indexBuffer = Buffer.alloc(4);
+  indexBuffer.writeUInt32LE(poolIndex);
+  [poolPubkey, poolBump] = await anchor.web3.PublicKey.findProgramAddress(
+    [Buffer.from("pool"), stakingMintPubkey.toBuffer(), mintRewards.toBuffer(), indexBuffer],
+    programID
+  );
 
   let tokenAccounts = await provider.connection.getParsedTokenAccountsByOwner(provider.wallet.publicKey, {mint: stakingMintPubkey});
 
@@ -44,51 +49,72 @@ const initializeMints = async () => {
   stakingTokenPubkey = stakingTokenAccountInfo.address;
 }
 
+const initializeRegistry = async () => {
+    const [registryPubkey, registryNonce] = await anchor.web3.PublicKey.findProgramAddress(
+        [Buffer.from("registry")],
+        programID
+    );
+
+    let registryInfo = await provider.connection.getAccountInfo(registryPubkey);
+    if (registryInfo === null) {
+        await program.rpc.initializeRegistry(
+            registryNonce,
+            {
+                accounts: {
+                    registry: registryPubkey,
+                    authority: provider.wallet.publicKey,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                },
+            }
+        );
+    }
+    return registryPubkey;
+}
+
 const initializePool = async () => {
     await initializeMints();
+    let registryPubkey = await initializeRegistry();
     
   const [
         _poolSigner,
         _nonce,
     ] = await anchor.web3.PublicKey.findProgramAddress(
-        [poolKeypair.publicKey.toBuffer()],
+        [poolPubkey.toBuffer()],
         programID
     );
     let poolSigner = _poolSigner;
     let poolNonce = _nonce;
 
-    let stakingTokenPoolVault = await stakingMintObject.createAccount(poolSigner);
-    let mintRewardsVault = await rewardsMintObject.createAccount(poolSigner);
-
-    const [
-        _vaultPubkey,
-        _vaultNonce,
-    ] = await anchor.web3.PublicKey.findProgramAddress(
-        [provider.wallet.publicKey.toBuffer(), poolKeypair.publicKey.toBuffer()],
+    const [stakingTokenPoolVault] = await anchor.web3.PublicKey.findProgramAddress(
+        [Buffer.from("staking_vault"), poolPubkey.toBuffer()],
+        programID
+    );
+    const [mintRewardsVault] = await anchor.web3.PublicKey.findProgramAddress(
+        [Buffer.from("reward_a_vault"), poolPubkey.toBuffer()],
         programID
     );
-    let vaultPubkey = _vaultPubkey;
-    let vaultNonce = _vaultNonce;
 
-    await program.rpc.initialize(
+    await program.rpc.createPool(
+        poolIndex,
+        poolBump,
         poolNonce,
         {
             accounts: {
+                registry: registryPubkey,
                 authority: provider.wallet.publicKey,
                 stakingMint: stakingMintObject.publicKey,
                 stakingVault: stakingTokenPoolVault,
                 rewardAMint: rewardsMintObject.publicKey,
                 rewardAVault: mintRewardsVault,
                 poolSigner: poolSigner,
-                pool: poolKeypair.publicKey,
+                pool: poolPubkey,
                 tokenProgram: TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+                rent: anchor.web3.SYSVAR_RENT_PUBKEY,
             },
-            signers: [poolKeypair],
-            instructions: [
-                await program.account.pool.createInstruction(poolKeypair, ),
-            ],
         }
     );
+    console.log("Pool: ", poolPubkey.toString());
     console.log("Successfully initialized!");
 }
 
@@ -1,26 +1,549 @@
+//! Single and multi reward staking pools.
+//!
+//! Other programs can own a `User` by passing one of their PDAs as `owner`
+//! and signing for it with `invoke_signed`. Build against the `cpi` feature
+//! and call `spl_staking::cpi::{create_user, stake, unstake, claim}`:
+//!
+//! * `create_user` takes a separate `payer`, so the owning PDA needs no lamports.
+//! * `stake` and `unstake` move tokens from and to a token account owned by the PDA.
+//! * `claim` pays the PDA's associated token accounts. Create them up front,
+//!   since creating them from `claim` would charge the PDA.
+//!
+//! `find_user_address` and `find_pool_signer_address` derive the accounts to
+//! pass, and `unlock_time` tells when `unstake` opens up.
+
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::{clock, program_option::COption};
+use anchor_lang::ZeroCopy;
+use anchor_lang::solana_program::clock;
+use anchor_lang::solana_program::keccak;
 use anchor_spl::token::{self, TokenAccount, Token, Mint};
+use anchor_spl::associated_token::{self, AssociatedToken};
+use std::cell::RefMut;
 use std::convert::Into;
 use std::convert::TryInto;
 
+pub mod metadata;
+
 declare_id!("6CFvHBzhteMDyjUyqwvpd8qVshsfByXuSysGK9pNq5yu");
 
+/// Precision of the reward A per token accumulator.
+const PRECISION: u128 = u64::MAX as u128;
+
+/// Reward campaigns must run at least a day.
+const MIN_REWARD_DURATION: u64 = 24 * 60 * 60;
+
+/// Stake stays locked this long after the last deposit, unless the pool sets a shorter lock.
+const UNSTAKE_LOCK_PERIOD: u64 = 30 * 24 * 60 * 60;
+
+/// Longest a vote escrow lock can run, voting power is full at this duration.
+pub const MAX_LOCK_DURATION: u64 = 4 * 365 * 24 * 60 * 60;
+
+/// Gauge votes are split in basis points of a voter's power.
+pub const MAX_GAUGE_VOTE_BPS: u16 = 10_000;
+
+/// Share of their stake unboosted users earn reward A on, so a full boost is 2.5x.
+pub const BOOST_BASE_BPS: u64 = 4_000;
+
+/// Scale of `User.boost_share`.
+pub const BOOST_SHARE_PRECISION: u64 = 1_000_000_000;
+
+/// Number of named tiers above `Tier::None`.
+pub const TIER_COUNT: usize = 4;
+
+/// Highest annual reward B rate a tier can pay, in basis points of the stake.
+pub const MAX_TIER_REWARD_B_BPS: u64 = 10_000;
+
+/// Scale of `Pool.slash_index`, the index of an unslashed pool.
+pub const SLASH_INDEX_PRECISION: u64 = 1_000_000_000_000_000_000;
+
+/// Offset of `unrelinquished_votes_count` in an SPL Governance token owner record.
+pub const UNRELINQUISHED_VOTES_OFFSET: usize = 105;
+
+/// Layout version written to new and migrated pools.
+pub const POOL_VERSION: u8 = 7;
+
+/// Layout version written to new and migrated users.
+pub const USER_VERSION: u8 = 5;
+
+/// Address of the `User` account of `owner` in `pool`.
+pub fn find_user_address(owner: &Pubkey, pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[owner.as_ref(), pool.as_ref()], &ID)
+}
+
+/// Address of the signer owning the vaults of `pool`.
+pub fn find_pool_signer_address(pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[pool.as_ref()], &ID)
+}
+
+/// The upgradeable loader's program data account of this program.
+pub fn find_program_data_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ID.as_ref()], &solana_program::bpf_loader_upgradeable::ID)
+}
+
+/// Upgrade authority stored in `program_data`, `None` once the program is immutable.
+pub fn program_upgrade_authority(program_data: &AccountInfo) -> Option<Pubkey> {
+    if program_data.owner != &solana_program::bpf_loader_upgradeable::ID {
+        return None;
+    }
+    let data = program_data.try_borrow_data().ok()?;
+    //bincode layout: u32 variant (3 is ProgramData), u64 slot, Option<Pubkey>
+    if data.len() < 45 || data[0..4] != [3, 0, 0, 0] || data[12] != 1 {
+        return None;
+    }
+    Some(Pubkey::new(&data[13..45]))
+}
+
+/// The first time stake deposited in `pool` at `stake_time` can be withdrawn.
+pub fn unlock_time(pool: &Pool, stake_time: u64) -> u64 {
+    stake_time.checked_add(pool.unstake_lock_period()).unwrap()
+}
+
+/// Allowlist leaf for `owner`, `max_stake` is zero for no per wallet cap.
+pub fn allowlist_leaf(owner: &Pubkey, max_stake: u64) -> [u8; 32] {
+    keccak::hashv(&[owner.as_ref(), &max_stake.to_le_bytes()]).0
+}
+
+/// Whether `proof` links `leaf` to `root`. Pairs are hashed in sorted order.
+pub fn verify_allowlist_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let mut node = leaf;
+    for sibling in proof.iter() {
+        node = if node <= *sibling {
+            keccak::hashv(&[&node, sibling]).0
+        } else {
+            keccak::hashv(&[sibling, &node]).0
+        };
+    }
+    node == root
+}
+
+/// Fails while `owner` has votes in the pool's governance realm that are not
+/// relinquished, so stake counted in a vote cannot leave and vote again.
+/// Expects the realm and the owner's token owner record at the front of
+/// `remaining_accounts` when the pool has a realm.
+pub fn check_no_outstanding_votes(
+    pool: &Pool,
+    owner: &Pubkey,
+    remaining_accounts: &[AccountInfo],
+) -> Result<()> {
+    if pool.governance_realm == Pubkey::default() {
+        return Ok(());
+    }
+    let (realm, token_owner_record) = match remaining_accounts {
+        [realm, token_owner_record, ..] => (realm, token_owner_record),
+        _ => return Err(ErrorCode::GovernanceAccountsMismatch.into()),
+    };
+    if realm.key() != pool.governance_realm {
+        return Err(ErrorCode::GovernanceAccountsMismatch.into());
+    }
+    //the record lives at a pda of the governance program owning the realm
+    let (expected, _) = Pubkey::find_program_address(
+        &[
+            b"governance",
+            realm.key.as_ref(),
+            pool.staking_mint.as_ref(),
+            owner.as_ref(),
+        ],
+        realm.owner,
+    );
+    if token_owner_record.key() != expected {
+        return Err(ErrorCode::GovernanceAccountsMismatch.into());
+    }
+    //an owner that never joined the realm has no votes
+    if token_owner_record.data_is_empty() {
+        return Ok(());
+    }
+    if token_owner_record.owner != realm.owner {
+        return Err(ErrorCode::GovernanceAccountsMismatch.into());
+    }
+    let data = token_owner_record.try_borrow_data()?;
+    let count = data
+        .get(UNRELINQUISHED_VOTES_OFFSET..UNRELINQUISHED_VOTES_OFFSET + 4)
+        .ok_or(ErrorCode::GovernanceAccountsMismatch)?;
+    if count != [0, 0, 0, 0] {
+        return Err(ErrorCode::VotesOutstanding.into());
+    }
+
+    Ok(())
+}
+
+/// Voting power of a lock at `timestamp`, decaying linearly to zero at `lock_end`.
+pub fn voting_power(amount: u64, lock_end: u64, timestamp: u64) -> u64 {
+    if timestamp >= lock_end {
+        return 0;
+    }
+    let remaining = lock_end - timestamp;
+    (amount as u128)
+        .checked_mul(remaining.min(MAX_LOCK_DURATION) as u128)
+        .unwrap()
+        .checked_div(MAX_LOCK_DURATION as u128)
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+/// Epoch of `controller` at `timestamp`.
+pub fn gauge_epoch(controller: &GaugeController, timestamp: u64) -> u64 {
+    timestamp.saturating_sub(controller.genesis_time) / controller.epoch_duration
+}
+
+/// Moves the controller's vote totals to `epoch`, keeping the previous epoch's as `last_total_weight`.
+pub fn roll_gauge_controller(controller: &mut GaugeController, epoch: u64) {
+    if controller.epoch == epoch {
+        return;
+    }
+    controller.last_total_weight = if controller.epoch + 1 == epoch {
+        controller.total_weight
+    } else {
+        0
+    };
+    controller.total_weight = 0;
+    controller.epoch = epoch;
+}
+
+/// Moves a gauge's votes to `epoch`, keeping the previous epoch's as `last_weight`.
+pub fn roll_gauge(gauge: &mut Gauge, epoch: u64) {
+    if gauge.epoch == epoch {
+        return;
+    }
+    gauge.last_weight = if gauge.epoch + 1 == epoch {
+        gauge.weight
+    } else {
+        0
+    };
+    gauge.weight = 0;
+    gauge.epoch = epoch;
+}
+
+pub fn last_time_reward_applicable(period_finish: u64, current_time: u64) -> u64 {
+    std::cmp::min(current_time, period_finish)
+}
+
+pub fn accrue_per_token(
+    per_token_stored: u128,
+    last_update_time: u64,
+    rate: u64,
+    total_staked: u64,
+    last_time_reward_applicable: u64,
+) -> u128 {
+    if total_staked == 0 {
+        return per_token_stored;
+    }
+
+    let time_period = (last_time_reward_applicable as u128)
+        .saturating_sub(last_update_time as u128);
+
+    per_token_stored
+        .checked_add(
+            time_period
+                .checked_mul(rate as u128)
+                .unwrap()
+                .checked_mul(PRECISION)
+                .unwrap()
+                .checked_div(total_staked as u128)
+                .unwrap(),
+        )
+        .unwrap()
+}
+
+/// Reward a campaign emits at `rate` between `last_update_time` and `last_time_reward_applicable`.
+pub fn emitted(rate: u64, last_update_time: u64, last_time_reward_applicable: u64) -> u64 {
+    last_time_reward_applicable
+        .saturating_sub(last_update_time)
+        .checked_mul(rate)
+        .unwrap()
+}
+
+/// Fails unless `balance`, less what is already owed, pays `rate` for all of `duration`.
+pub fn check_reward_funded(rate: u64, balance: u64, owed: u64, duration: u64) -> Result<()> {
+    if rate > balance.saturating_sub(owed).checked_div(duration).unwrap() {
+        return Err(ErrorCode::RewardTooHigh.into());
+    }
+
+    Ok(())
+}
+
+pub fn reward_per_token(pool: &Pool, last_time_reward_applicable: u64) -> u128 {
+    accrue_per_token(
+        pool.reward_a_per_token_stored,
+        pool.last_update_time,
+        pool.reward_a_rate,
+        pool.working_supply,
+        last_time_reward_applicable,
+    )
+}
+
+pub fn earned(balance_staked: u64, per_token_stored: u128, per_token_complete: u128) -> u64 {
+    (balance_staked as u128)
+        .checked_mul(per_token_stored.checked_sub(per_token_complete).unwrap())
+        .unwrap()
+        .checked_div(PRECISION)
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+/// Highest tier whose threshold `balance_staked` reaches.
+pub fn tier_for(pool: &Pool, balance_staked: u64) -> Tier {
+    let tiers = [Tier::Bronze, Tier::Silver, Tier::Gold, Tier::Platinum];
+    let thresholds = pool.tier_thresholds;
+    let mut tier = Tier::None;
+    for (i, threshold) in thresholds.iter().enumerate() {
+        if *threshold > 0 && balance_staked >= *threshold {
+            tier = tiers[i];
+        }
+    }
+    tier
+}
+
+/// Whether stake moved from `from` would still be locked or cooling down when it
+/// could leave `to`, no cooldown at all counting as the latest.
+pub fn outlasts_recipient(pool: &Pool, from: &User, to: &User) -> bool {
+    if from.stake_time > to.stake_time {
+        return true;
+    }
+    pool.cooldown_duration > 0
+        && to.cooldown_start != 0
+        && (from.cooldown_start == 0 || from.cooldown_start > to.cooldown_start)
+}
+
+/// Loyalty bonus in basis points, one step per full `loyalty_step_duration` staked.
+pub fn loyalty_bps(pool: &Pool, user: &User, timestamp: u64) -> u64 {
+    if pool.loyalty_step_duration == 0 {
+        return 0;
+    }
+    let steps = timestamp.saturating_sub(user.loyalty_start) / pool.loyalty_step_duration;
+    std::cmp::min(steps.saturating_mul(pool.loyalty_step_bps), pool.loyalty_max_bps)
+}
+
+/// Moves the loyalty clock forward by the share of the stake being withdrawn.
+pub fn reset_loyalty(user: &mut User, withdrawn: u64, timestamp: u64) {
+    if user.balance_staked == 0 {
+        return;
+    }
+    let held = timestamp.saturating_sub(user.loyalty_start);
+    let forfeited: u64 = (held as u128)
+        .checked_mul(withdrawn as u128)
+        .unwrap()
+        .checked_div(user.balance_staked as u128)
+        .unwrap()
+        .try_into()
+        .unwrap();
+    user.loyalty_start = user.loyalty_start.checked_add(forfeited).unwrap();
+}
+
+/// Moves the loyalty clock forward to the stake weighted start of the old and deposited stake.
+pub fn dilute_loyalty(user: &mut User, deposited: u64, timestamp: u64) {
+    let held = timestamp.saturating_sub(user.loyalty_start);
+    let forfeited: u64 = (held as u128)
+        .checked_mul(deposited as u128)
+        .unwrap()
+        .checked_div((user.balance_staked as u128).checked_add(deposited as u128).unwrap())
+        .unwrap()
+        .try_into()
+        .unwrap();
+    user.loyalty_start = user.loyalty_start.checked_add(forfeited).unwrap();
+}
+
+/// Stake reward A is paid on, Curve style: `min(0.4 * balance + 0.6 * total * boost_share, balance)`,
+/// then raised by the loyalty bonus.
+pub fn working_balance(pool: &Pool, user: &User, timestamp: u64) -> u64 {
+    let boosted = boosted_balance(pool, user);
+    (boosted as u128)
+        .checked_mul(10_000 + loyalty_bps(pool, user, timestamp) as u128)
+        .unwrap()
+        .checked_div(10_000)
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+fn boosted_balance(pool: &Pool, user: &User) -> u64 {
+    if pool.boost_pool == Pubkey::default() {
+        return user.balance_staked;
+    }
+
+    let base = (user.balance_staked as u128)
+        .checked_mul(BOOST_BASE_BPS as u128)
+        .unwrap()
+        / 10_000;
+    let boosted = (pool.total_staked as u128)
+        .checked_mul(user.boost_share as u128)
+        .unwrap()
+        .checked_mul((10_000 - BOOST_BASE_BPS) as u128)
+        .unwrap()
+        / 10_000
+        / BOOST_SHARE_PRECISION as u128;
+    std::cmp::min(base.checked_add(boosted).unwrap(), user.balance_staked as u128)
+        .try_into()
+        .unwrap()
+}
+
+/// Pools from before boosts count every staked token as working, and pools
+/// from before slashing start unslashed.
+pub fn backfill_pool_reward_fields(pool: &mut Pool) {
+    if pool.version < 4 {
+        pool.working_supply = pool.total_staked;
+        pool.version = 4;
+    }
+    //tiers stay off until the authority configures them
+    if pool.version < 5 {
+        pool.version = 5;
+    }
+    if pool.version < 6 {
+        pool.slash_index = SLASH_INDEX_PRECISION;
+        pool.version = 6;
+    }
+}
+
+/// Users from before boosts count their whole stake as working balance, their
+/// loyalty clock starts at the last deposit, and users from before slashing
+/// have missed none.
+pub fn backfill_user_reward_fields(user: &mut User) {
+    if user.version < 2 {
+        user.working_balance = user.balance_staked;
+        user.version = 2;
+    }
+    if user.version < 3 {
+        user.loyalty_start = user.stake_time;
+        user.version = 3;
+    }
+    //referrals start unlinked
+    if user.version < 4 {
+        user.version = 4;
+    }
+    if user.version < 5 {
+        user.slash_index = SLASH_INDEX_PRECISION;
+        user.version = 5;
+    }
+}
+
+/// Scales `amount` staked at `from_index` down to the pool's `to_index`.
+pub fn apply_slash_index(amount: u64, from_index: u64, to_index: u64) -> u64 {
+    (amount as u128)
+        .checked_mul(to_index as u128)
+        .unwrap()
+        .checked_div(from_index as u128)
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+/// `update_rewards` for instructions that write the user back, emitting `TierChanged`
+/// when the update moves the user to another tier. Views call `update_rewards` directly.
+pub fn update_user_rewards(pool: &mut Pool, user: &mut User) -> Result<()> {
+    let old_tier = user.tier();
+    update_rewards(pool, Some(user))?;
+    if user.tier() != old_tier {
+        emit!(TierChanged {
+            pool: user.pool,
+            owner: user.owner,
+            old_tier,
+            new_tier: user.tier(),
+            balance_staked: user.balance_staked,
+        });
+    }
+
+    Ok(())
+}
+
 pub fn update_rewards(
-    user: Option<&mut Box<Account<User>>>,
+    pool: &mut Pool,
+    user: Option<&mut User>,
 ) -> Result<()> {
+    backfill_pool_reward_fields(pool);
+
+    let clock = clock::Clock::get().unwrap();
+    let last_time_reward_applicable = clock.unix_timestamp;
+    let last_time_reward_a_applicable = self::last_time_reward_applicable(
+        pool.period_finish,
+        last_time_reward_applicable as u64,
+    );
+
+    //only what the accumulator hands out is owed, nothing accrues to an empty pool
+    if pool.working_supply > 0 {
+        pool.reward_a_owed = pool.reward_a_owed.checked_add(
+                emitted(pool.reward_a_rate, pool.last_update_time, last_time_reward_a_applicable))
+            .unwrap();
+    }
+    pool.reward_a_per_token_stored = reward_per_token(pool, last_time_reward_a_applicable);
+    pool.last_update_time = last_time_reward_a_applicable;
+
+    let total_staked = pool.total_staked;
+    for (i, reward) in pool.extra_rewards.iter_mut().enumerate() {
+        if reward.mint == Pubkey::default() {
+            continue;
+        }
+        let last_time_applicable = self::last_time_reward_applicable(
+            reward.period_finish,
+            last_time_reward_applicable as u64,
+        );
+        if total_staked > 0 {
+            pool.extra_rewards_owed[i] = pool.extra_rewards_owed[i].checked_add(
+                    emitted(reward.reward_rate, reward.last_update_time, last_time_applicable))
+                .unwrap();
+        }
+        reward.reward_per_token_stored = accrue_per_token(
+            reward.reward_per_token_stored,
+            reward.last_update_time,
+            reward.reward_rate,
+            total_staked,
+            last_time_applicable,
+        );
+        reward.last_update_time = last_time_applicable;
+    }
+
     if let Some(u) = user {
-        let clock = clock::Clock::get().unwrap();
-        let last_time_reward_applicable = clock.unix_timestamp;
-
-        if u.balance_staked > 1100_000_000_000 {
-            // u.reward_a = u.reward_a.checked_add(
-            //         ((last_time_reward_applicable as u64)
-            //                                 .checked_sub(u.last_update_time as u64)
-            //                                 .unwrap())
-            //                 .checked_mul(u.reward_a_rate as u64)
-            //                 .unwrap())
-            //         .unwrap();
+        backfill_user_reward_fields(u);
+        //stake is a share of the pool, slashes since the last update shrink it pro rata
+        if u.slash_index != pool.slash_index {
+            u.balance_staked = apply_slash_index(u.balance_staked, u.slash_index, pool.slash_index);
+            u.working_balance = apply_slash_index(u.working_balance, u.slash_index, pool.slash_index);
+            u.slash_index = pool.slash_index;
+        }
+        let reward_a_earned = earned(u.working_balance, pool.reward_a_per_token_stored, u.reward_a_per_token_complete);
+        u.reward_a = u.reward_a.checked_add(reward_a_earned).unwrap();
+        //the referrer's share comes on top, the referee keeps everything they earned
+        if u.referrer != Pubkey::default() && pool.referral_bps > 0 {
+            let referral_share = (reward_a_earned as u128)
+                .checked_mul(pool.referral_bps as u128)
+                .unwrap()
+                / 10_000;
+            u.referral_pending = u.referral_pending.checked_add(referral_share.try_into().unwrap()).unwrap();
+        }
+        u.reward_a_per_token_complete = pool.reward_a_per_token_stored;
+
+        for (i, reward) in pool.extra_rewards.iter().enumerate() {
+            u.extra_rewards[i] = u.extra_rewards[i].checked_add(
+                    earned(u.balance_staked, reward.reward_per_token_stored, u.extra_reward_per_token_complete[i]))
+                .unwrap();
+            u.extra_reward_per_token_complete[i] = reward.reward_per_token_stored;
+        }
+
+        if pool.has_tiers() {
+            u.reward_b = u.reward_b.checked_add(
+                    ((last_time_reward_applicable as u64)
+                        .checked_sub(u.last_update_time)
+                        .unwrap())
+                        .checked_mul(u.reward_b_rate)
+                        .unwrap())
+                .unwrap();
+
+            //tiers replace the flat 1100 token threshold with a rate per tier
+            let tier = tier_for(pool, u.balance_staked);
+            u.reward_b_rate = match tier {
+                Tier::None => 0,
+                _ => (u.balance_staked as u128)
+                    .checked_mul(pool.tier_reward_b_bps[tier as usize - 1] as u128)
+                    .unwrap()
+                    .checked_div(10_000 * 365 * 24 * 60 * 60)
+                    .unwrap()
+                    .try_into()
+                    .unwrap(),
+            };
+            u.last_update_time = last_time_reward_applicable as u64;
+
+            u.tier = tier as u8;
+        } else if u.balance_staked > 1100_000_000_000 {
             u.reward_b = u.reward_b.checked_add(
                     ((last_time_reward_applicable as u64)
                                             .checked_sub(u.last_update_time as u64)
@@ -29,551 +552,4106 @@ pub fn update_rewards(
                             .unwrap())
                     .unwrap();
 
-            // u.reward_a_rate = u.balance_staked
-            //                     .checked_mul(1_000_000_000).unwrap()
-            //                     .checked_mul(12).unwrap()
-            //                     .checked_div(100 * 365 * 24 * 60 * 60).unwrap();
             u.reward_b_rate = u.balance_staked
                                 .checked_div(365 * 24 * 60 * 60).unwrap();
 
             u.last_update_time = last_time_reward_applicable as u64;
         }
+
+        //an empty position has no loyalty to keep, the clock starts with the next deposit
+        if u.balance_staked == 0 {
+            u.loyalty_start = last_time_reward_applicable as u64;
+        }
+
+        //accrued above on the old working balance, the new one applies from now
+        let working_balance = self::working_balance(pool, u, last_time_reward_applicable as u64);
+        pool.working_supply = pool
+            .working_supply
+            .checked_sub(u.working_balance)
+            .unwrap()
+            .checked_add(working_balance)
+            .unwrap();
+        u.working_balance = working_balance;
     }
     
     Ok(())
 }
 
-#[program]
-pub mod spl_staking {
-    use super::*;
-    pub fn initialize(
-        ctx: Context<Initialize>,
-        pool_nonce: u8,
-        ) -> ProgramResult {
-
-        let pool = &mut ctx.accounts.pool;
-
-        pool.authority = ctx.accounts.authority.key();
-        pool.nonce = pool_nonce;
-        pool.paused = false;
-        pool.staking_mint = ctx.accounts.staking_mint.key();
-        pool.staking_vault = ctx.accounts.staking_vault.key();
-        pool.reward_a_mint = ctx.accounts.reward_a_mint.key();
-        pool.reward_a_vault = ctx.accounts.reward_a_vault.key();
-        pool.user_stake_count = 0;
-        
-        Ok(())
+/// Reward A vested on the user's current schedule by `timestamp`.
+pub fn vested_amount(user: &User, timestamp: u64) -> u64 {
+    if timestamp < user.vesting_cliff_end {
+        return 0;
     }
-
-    pub fn create_user(ctx: Context<CreateUser>, nonce: u8) -> Result<()> {
-        let user = &mut ctx.accounts.user;
-        user.pool = *ctx.accounts.pool.to_account_info().key;
-        user.owner = *ctx.accounts.owner.key;
-
-        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
-
-        user.stake_time = current_time;
-        user.last_update_time = current_time;
-
-        user.reward_a_rate = 0;
-        user.reward_b_rate = 0;
-        user.reward_a = 0;
-        user.reward_b = 0;
-
-        user.balance_staked = 0;
-        user.nonce = nonce;
-
-        let pool = &mut ctx.accounts.pool;
-        pool.user_stake_count = pool.user_stake_count.checked_add(1).unwrap();
-
-        Ok(())
+    if timestamp >= user.vesting_end {
+        return user.vesting_total;
     }
+    //linear from the start, the cliff only holds release back
+    (user.vesting_total as u128)
+        .checked_mul(timestamp.checked_sub(user.vesting_start).unwrap() as u128)
+        .unwrap()
+        .checked_div(user.vesting_end.checked_sub(user.vesting_start).unwrap() as u128)
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
 
-    pub fn pause(ctx: Context<Pause>) -> Result<()> {
-        let pool = &mut ctx.accounts.pool;
-        pool.paused = true;
+/// Reward A `release_vested` would pay at `timestamp`.
+pub fn releasable_amount(user: &User, timestamp: u64) -> u64 {
+    user.vesting_unlocked
+        .checked_add(vested_amount(user, timestamp).checked_sub(user.vesting_released).unwrap())
+        .unwrap()
+}
 
-        Ok(())
+/// Puts `amount` of claimed reward A on a vesting schedule. Whatever has vested
+/// stays releasable, whatever is still locked keeps its end and cliff, weighted
+/// against the new amount's, so earlier claims do not restart.
+pub fn add_vesting(pool: &Pool, user: &mut User, amount: u64, timestamp: u64) {
+    let vested = vested_amount(user, timestamp);
+    user.vesting_unlocked = user
+        .vesting_unlocked
+        .checked_add(vested.checked_sub(user.vesting_released).unwrap())
+        .unwrap();
+    let locked = user.vesting_total.checked_sub(vested).unwrap();
+    let end = timestamp.checked_add(pool.vesting_duration).unwrap();
+    let cliff_end = timestamp.checked_add(pool.vesting_cliff).unwrap();
+
+    user.vesting_total = locked.checked_add(amount).unwrap();
+    user.vesting_released = 0;
+    user.vesting_start = timestamp;
+    if locked == 0 {
+        user.vesting_cliff_end = cliff_end;
+        user.vesting_end = end;
+    } else {
+        //the locked remainder vests from now, so a cliff already passed counts as now
+        user.vesting_cliff_end = weighted_time(locked, user.vesting_cliff_end.max(timestamp), amount, cliff_end);
+        user.vesting_end = weighted_time(locked, user.vesting_end, amount, end);
     }
+}
 
-    pub fn unpause(ctx: Context<Unpause>) -> Result<()> {
-        let pool = &mut ctx.accounts.pool;
-        pool.paused = false;
+/// Average of two times weighted by the amounts due at each, rounded up.
+pub fn weighted_time(amount_a: u64, time_a: u64, amount_b: u64, time_b: u64) -> u64 {
+    let total = (amount_a as u128).checked_add(amount_b as u128).unwrap();
+    (amount_a as u128)
+        .checked_mul(time_a as u128)
+        .unwrap()
+        .checked_add((amount_b as u128).checked_mul(time_b as u128).unwrap())
+        .unwrap()
+        .checked_add(total - 1)
+        .unwrap()
+        .checked_div(total)
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
 
-        Ok(())
+/// Shares `amount` of reward A already in the vault among working balances at once.
+pub fn distribute_reward_a(pool: &mut Pool, amount: u64) {
+    //with nothing staked it stays in the vault unallocated
+    if pool.working_supply == 0 {
+        return;
     }
+    pool.reward_a_per_token_stored = pool
+        .reward_a_per_token_stored
+        .checked_add(
+            (amount as u128)
+                .checked_mul(PRECISION)
+                .unwrap()
+                .checked_div(pool.working_supply as u128)
+                .unwrap(),
+        )
+        .unwrap();
+}
 
-    pub fn stake(ctx: Context<Stake>, amount: u64) -> Result<()> {
-        if amount == 0 {
-            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+/// Pays each active extra reward from `(vault, destination)` pairs in `remaining_accounts`,
+/// in the order the rewards are listed on the pool.
+pub fn pay_extra_rewards<'info>(
+    pool: &mut Pool,
+    user: &mut User,
+    recipient: Pubkey,
+    remaining_accounts: &[AccountInfo<'info>],
+    token_program: AccountInfo<'info>,
+    pool_signer: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mut accounts = remaining_accounts.iter();
+    for (i, reward) in pool.extra_rewards.iter().enumerate() {
+        if reward.mint == Pubkey::default() {
+            continue;
         }
 
-        let pool = &mut ctx.accounts.pool;
-        if pool.paused {
-            return Err(ErrorCode::PoolPaused.into());
+        let vault_info = accounts.next().ok_or(ErrorCode::MissingRewardAccounts)?;
+        let destination_info = accounts.next().ok_or(ErrorCode::MissingRewardAccounts)?;
+        if vault_info.key() != reward.vault {
+            return Err(ErrorCode::InvalidRewardVault.into());
+        }
+        let vault: Account<TokenAccount> = Account::try_from(vault_info)?;
+        let destination: Account<TokenAccount> = Account::try_from(destination_info)?;
+        if destination.mint != reward.mint {
+            return Err(ErrorCode::RewardAccountMintMismatch.into());
+        }
+        if destination.owner != recipient {
+            return Err(ErrorCode::RewardAccountOwnerMismatch.into());
         }
 
-        ctx.accounts.user.balance_staked = ctx.accounts.user.balance_staked.checked_add(amount).unwrap();
-
-        let user_opt = Some(&mut ctx.accounts.user);
-        update_rewards(
-            user_opt,
-        )
-        .unwrap();
-        
-
-        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
-        ctx.accounts.user.stake_time = current_time;
+        //whatever the vault cannot cover stays owed to the user
+        let reward_amount = user.extra_rewards[i].min(vault.amount);
+        user.extra_rewards[i] = user.extra_rewards[i].checked_sub(reward_amount).unwrap();
+        pool.extra_rewards_owed[i] = pool.extra_rewards_owed[i].saturating_sub(reward_amount);
 
-        // Transfer tokens into the stake vault.
-        {
-            let cpi_ctx = CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
+        if reward_amount > 0 {
+            let cpi_ctx = CpiContext::new_with_signer(
+                token_program.clone(),
                 token::Transfer {
-                    from: ctx.accounts.stake_from_account.to_account_info(),
-                    to: ctx.accounts.staking_vault.to_account_info(),
-                    authority: ctx.accounts.owner.to_account_info(), //todo use user account as signer
+                    from: vault_info.clone(),
+                    to: destination_info.clone(),
+                    authority: pool_signer.clone(),
                 },
+                signer_seeds,
             );
-            token::transfer(cpi_ctx, amount)?;
+            token::transfer(cpi_ctx, reward_amount)?;
         }
-
-        Ok(())
     }
 
-    pub fn unstake(ctx: Context<Stake>, spt_amount: u64) -> Result<()> {
-        if spt_amount == 0 {
-            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
-        }
-        
-        if ctx.accounts.user.balance_staked < spt_amount {
-            return Err(ErrorCode::InsufficientFundUnstake.into());
-        }
+    Ok(())
+}
 
-        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
-        if current_time < ctx.accounts.user.stake_time.checked_add(30 * 24 * 60 * 60).unwrap() {
-            return Err(ErrorCode::NotEnoughUnstakePeriod.into());
+/// Pays out `claim`. With vesting on, reward A goes on the user's vesting schedule
+/// instead, or with `instant` is paid now less the haircut.
+pub fn claim_rewards<'info>(
+    accounts: &ClaimReward<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    instant: bool,
+) -> Result<()> {
+    let pool = &mut accounts.pool.load_mut()?;
+    pool.record_activity();
+    let user = &mut accounts.user.load_mut()?;
+    update_user_rewards(pool, user).unwrap();
+
+    let seeds = &[
+        accounts.pool.to_account_info().key.as_ref(),
+        &[pool.nonce],
+    ];
+    let pool_signer = &[&seeds[..]];
+
+    if user.reward_a > 0 && pool.has_vesting() {
+        if instant {
+            //the haircut stays in the vault and goes to the pool's stakers
+            let haircut: u64 = (user.reward_a as u128)
+                .checked_mul(pool.instant_claim_haircut_bps as u128)
+                .unwrap()
+                .checked_div(10_000)
+                .unwrap()
+                .try_into()
+                .unwrap();
+            user.reward_a = user.reward_a.checked_sub(haircut).unwrap();
+            distribute_reward_a(pool, haircut);
+        } else {
+            let clock = clock::Clock::get().unwrap();
+            let amount = user.reward_a;
+            user.reward_a = 0;
+            add_vesting(pool, user, amount, clock.unix_timestamp as u64);
         }
+    }
 
-        ctx.accounts.user.balance_staked = ctx.accounts.user.balance_staked.checked_sub(spt_amount).unwrap();
-        
-        let user_opt = Some(&mut ctx.accounts.user);
-        update_rewards(
-            user_opt,
-        )
-        .unwrap();
+    pay_rewards(
+        pool,
+        user,
+        accounts.owner.key(),
+        &ClaimPayout {
+            reward_a_vault: &accounts.reward_a_vault,
+            reward_a_account: &accounts.reward_a_account,
+            staking_vault: &accounts.staking_vault,
+            reward_b_account: &accounts.reward_b_account,
+            pool_signer: accounts.pool_signer.to_account_info(),
+            token_program: accounts.token_program.to_account_info(),
+        },
+        pool_signer,
+        remaining_accounts,
+    )
+}
 
-        // Transfer tokens from the pool vault to user vault.
-        {
-            let seeds = &[
-                ctx.accounts.pool.to_account_info().key.as_ref(),
-                &[ctx.accounts.pool.nonce],
-            ];
-            let pool_signer = &[&seeds[..]];
+/// Vaults, destinations and signer a claim pays out through.
+pub struct ClaimPayout<'a, 'info> {
+    pub reward_a_vault: &'a Account<'info, TokenAccount>,
+    pub reward_a_account: &'a Account<'info, TokenAccount>,
+    pub staking_vault: &'a Account<'info, TokenAccount>,
+    pub reward_b_account: &'a Account<'info, TokenAccount>,
+    pub pool_signer: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+}
+
+/// Pays `user`'s accrued reward A, reward B and extra rewards to `recipient`, capped by the vaults.
+pub fn pay_rewards<'info>(
+    pool: &mut Pool,
+    user: &mut User,
+    recipient: Pubkey,
+    payout: &ClaimPayout<'_, 'info>,
+    signer_seeds: &[&[&[u8]]],
+    remaining_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    //whatever the vaults cannot cover stays owed to the user
+    if user.reward_a > 0 {
+        let reward_amount = user.reward_a.min(pool.reward_a_available(payout.reward_a_vault.amount));
+        user.reward_a = user.reward_a.checked_sub(reward_amount).unwrap();
+        pool.reward_a_owed = pool.reward_a_owed.saturating_sub(reward_amount);
 
+        if reward_amount > 0 {
             let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
+                payout.token_program.clone(),
                 token::Transfer {
-                    from: ctx.accounts.staking_vault.to_account_info(),
-                    to: ctx.accounts.stake_from_account.to_account_info(),
-                    authority: ctx.accounts.pool_signer.to_account_info(),
+                    from: payout.reward_a_vault.to_account_info(),
+                    to: payout.reward_a_account.to_account_info(),
+                    authority: payout.pool_signer.clone(),
                 },
-                pool_signer,
+                signer_seeds,
             );
-            token::transfer(cpi_ctx, spt_amount.try_into().unwrap())?;
+            token::transfer(cpi_ctx, reward_amount)?;
+            pool.total_rewards_a_paid = pool.total_rewards_a_paid.checked_add(reward_amount).unwrap();
         }
-
-        Ok(())
     }
 
-    pub fn withdraw(ctx: Context<Withdraw>, spt_amount: u64) -> Result<()> {
-        
-        // Transfer tokens from the pool vault to user vault.
-        {
-            let seeds = &[
-                ctx.accounts.pool.to_account_info().key.as_ref(),
-                &[ctx.accounts.pool.nonce],
-            ];
-            let pool_signer = &[&seeds[..]];
+    if user.reward_b > 0 {
+        let reward_amount = user.reward_b.min(pool.reward_b_available(payout.staking_vault.amount));
+        user.reward_b = user.reward_b.checked_sub(reward_amount).unwrap();
 
+        if reward_amount > 0 {
             let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
+                payout.token_program.clone(),
                 token::Transfer {
-                    from: ctx.accounts.staking_vault.to_account_info(),
-                    to: ctx.accounts.withdraw_account.to_account_info(),
-                    authority: ctx.accounts.pool_signer.to_account_info(),
+                    from: payout.staking_vault.to_account_info(),
+                    to: payout.reward_b_account.to_account_info(),
+                    authority: payout.pool_signer.clone(),
                 },
-                pool_signer,
+                signer_seeds,
             );
-            token::transfer(cpi_ctx, spt_amount.try_into().unwrap())?;
+            token::transfer(cpi_ctx, reward_amount)?;
+            pool.total_rewards_b_paid = pool.total_rewards_b_paid.checked_add(reward_amount).unwrap();
         }
-
-        Ok(())
     }
 
-    pub fn authorize_funder(ctx: Context<FunderChange>, funder_to_add: Pubkey) -> Result<()> {
-        if funder_to_add == ctx.accounts.pool.authority.key() {
-            return Err(ErrorCode::FunderAlreadyAuthorized.into());
+    pay_extra_rewards(
+        pool,
+        user,
+        recipient,
+        remaining_accounts,
+        payout.token_program.clone(),
+        payout.pool_signer.clone(),
+        signer_seeds,
+    )
+}
+
+/// Empties a pool vault into `refundee_account` and closes it to `refundee`.
+pub fn close_pool_vault<'info>(
+    token_program: &Program<'info, Token>,
+    vault: &Account<'info, TokenAccount>,
+    refundee_account: &Account<'info, TokenAccount>,
+    refundee: &AccountInfo<'info>,
+    pool_signer: &AccountInfo<'info>,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let ix = spl_token::instruction::transfer(
+        &spl_token::ID,
+        vault.to_account_info().key,
+        refundee_account.to_account_info().key,
+        pool_signer.key,
+        &[pool_signer.key],
+        vault.amount,
+    )?;
+    solana_program::program::invoke_signed(
+        &ix,
+        &[
+            token_program.to_account_info(),
+            vault.to_account_info(),
+            refundee_account.to_account_info(),
+            pool_signer.clone(),
+        ],
+        &[signer_seeds],
+    )?;
+    let ix = spl_token::instruction::close_account(
+        &spl_token::ID,
+        vault.to_account_info().key,
+        refundee.key,
+        pool_signer.key,
+        &[pool_signer.key],
+    )?;
+    solana_program::program::invoke_signed(
+        &ix,
+        &[
+            token_program.to_account_info(),
+            vault.to_account_info(),
+            refundee.clone(),
+            pool_signer.clone(),
+        ],
+        &[signer_seeds],
+    )
+}
+
+pub fn init_pool(
+    pool: &mut Pool,
+    authority: Pubkey,
+    nonce: u8,
+    staking_mint: Pubkey,
+    staking_vault: Pubkey,
+    reward_a_mint: Pubkey,
+    reward_a_vault: Pubkey,
+) {
+    pool.authority = authority;
+    pool.nonce = nonce;
+    pool.paused = false as u8;
+    pool.staking_mint = staking_mint;
+    pool.staking_vault = staking_vault;
+    pool.reward_a_mint = reward_a_mint;
+    pool.reward_a_vault = reward_a_vault;
+    pool.user_stake_count = 0;
+    pool.stake_for_resets_lock = false as u8;
+    pool.transfers_enabled = true as u8;
+    pool.kind = PoolKind::Fungible as u8;
+    pool.total_staked = 0;
+    pool.reward_a_rate = 0;
+    pool.reward_a_per_token_stored = 0;
+    pool.last_update_time = 0;
+    pool.period_finish = 0;
+    pool.extra_rewards = [ExtraReward::default(); MAX_EXTRA_REWARDS];
+    pool.slash_index = SLASH_INDEX_PRECISION;
+    pool.version = POOL_VERSION;
+    pool.record_activity();
+}
+
+pub fn init_user(
+    user: &mut User,
+    pool: &mut Pool,
+    pool_key: Pubkey,
+    owner: Pubkey,
+    nonce: u8,
+) {
+    //the user starts from the pool's current slash index
+    backfill_pool_reward_fields(pool);
+    user.pool = pool_key;
+    user.owner = owner;
+
+    let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+
+    user.stake_time = current_time;
+    user.last_update_time = current_time;
+    user.loyalty_start = current_time;
+
+    user.reward_a_rate = 0;
+    user.reward_b_rate = 0;
+    user.reward_a = 0;
+    user.reward_b = 0;
+
+    user.balance_staked = 0;
+    user.reward_a_per_token_complete = pool.reward_a_per_token_stored;
+    for (i, reward) in pool.extra_rewards.iter().enumerate() {
+        user.extra_rewards[i] = 0;
+        user.extra_reward_per_token_complete[i] = reward.reward_per_token_stored;
+    }
+    user.slash_index = pool.slash_index;
+    user.nonce = nonce;
+    user.version = USER_VERSION;
+
+    pool.user_stake_count = pool.user_stake_count.checked_add(1).unwrap();
+}
+
+pub fn update_nft_rewards(
+    user: &mut User,
+    nft_reward_rate: u64,
+) -> Result<()> {
+    let clock = clock::Clock::get().unwrap();
+    let current_time = clock.unix_timestamp as u64;
+
+    user.reward_a = user.reward_a.checked_add(
+            current_time
+                .checked_sub(user.last_update_time)
+                .unwrap()
+                .checked_mul(user.reward_a_rate)
+                .unwrap())
+        .unwrap();
+
+    //balance_staked counts escrowed nfts in nft pools
+    user.reward_a_rate = user.balance_staked.checked_mul(nft_reward_rate).unwrap();
+    user.last_update_time = current_time;
+
+    Ok(())
+}
+
+/// Loads an account created by `init_if_needed`, which has no discriminator
+/// until the instruction exits when it was just created.
+pub fn load_init_if_needed<'a, 'info, T: ZeroCopy + Owner>(
+    loader: &'a AccountLoader<'info, T>,
+) -> Result<RefMut<'a, T>> {
+    let is_new = loader.to_account_info().try_borrow_data()?[..8] == [0u8; 8];
+    if is_new {
+        Ok(loader.load_init()?)
+    } else {
+        Ok(loader.load_mut()?)
+    }
+}
+
+/// Hands a view result back to the caller through the return data.
+pub fn set_view_return<T: AnchorSerialize>(value: &T) {
+    solana_program::program::set_return_data(&value.try_to_vec().unwrap());
+}
+
+/// Grows a program account to `space` bytes, topping up rent from `payer`.
+/// New bytes are zeroed, which is the default of every appended field.
+pub fn realloc_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+) -> ProgramResult {
+    if account.data_len() >= space {
+        return Ok(());
+    }
+
+    let minimum_balance = Rent::get()?.minimum_balance(space);
+    let top_up = minimum_balance.saturating_sub(account.lamports());
+    if top_up > 0 {
+        solana_program::program::invoke(
+            &solana_program::system_instruction::transfer(payer.key, account.key, top_up),
+            &[
+                payer.clone(),
+                account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+
+    account.realloc(space, true)
+}
+
+#[program]
+pub mod spl_staking {
+    use super::*;
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        pool_nonce: u8,
+        ) -> ProgramResult {
+
+        init_pool(
+            &mut *ctx.accounts.pool.load_init()?,
+            ctx.accounts.authority.key(),
+            pool_nonce,
+            ctx.accounts.staking_mint.key(),
+            ctx.accounts.staking_vault.key(),
+            ctx.accounts.reward_a_mint.key(),
+            ctx.accounts.reward_a_vault.key(),
+        );
+
+        Ok(())
+    }
+
+    pub fn initialize_registry(ctx: Context<InitializeRegistry>, nonce: u8) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        registry.authority = ctx.accounts.authority.key();
+        registry.nonce = nonce;
+        registry.pools = Vec::new();
+
+        Ok(())
+    }
+
+    pub fn create_pool(
+        ctx: Context<CreatePool>,
+        _index: u32,
+        _pool_bump: u8,
+        pool_nonce: u8,
+        ) -> Result<()> {
+
+        let registry = &mut ctx.accounts.registry;
+        if registry.pools.len() >= MAX_REGISTERED_POOLS {
+            return Err(ErrorCode::RegistryFull.into());
         }
-        let funders = &mut ctx.accounts.pool.funders;
-        if funders.iter().any(|x| *x == funder_to_add) {
-            return Err(ErrorCode::FunderAlreadyAuthorized.into());
+        registry.pools.push(ctx.accounts.pool.key());
+
+        init_pool(
+            &mut *ctx.accounts.pool.load_init()?,
+            ctx.accounts.authority.key(),
+            pool_nonce,
+            ctx.accounts.staking_mint.key(),
+            ctx.accounts.staking_vault.key(),
+            ctx.accounts.reward_a_mint.key(),
+            ctx.accounts.reward_a_vault.key(),
+        );
+
+        Ok(())
+    }
+
+    pub fn initialize_nft_pool(
+        ctx: Context<InitializeNftPool>,
+        pool_nonce: u8,
+        nft_collection: Pubkey,
+        nft_reward_rate: u64,
+        ) -> ProgramResult {
+
+        let pool = &mut ctx.accounts.pool.load_init()?;
+
+        pool.authority = ctx.accounts.authority.key();
+        pool.nonce = pool_nonce;
+        pool.paused = false as u8;
+        pool.reward_a_mint = ctx.accounts.reward_a_mint.key();
+        pool.reward_a_vault = ctx.accounts.reward_a_vault.key();
+        pool.user_stake_count = 0;
+        pool.stake_for_resets_lock = false as u8;
+        //nft balances are tied to their escrow records and cannot move between users
+        pool.transfers_enabled = false as u8;
+        pool.kind = PoolKind::Nft as u8;
+        pool.nft_collection = nft_collection;
+        pool.nft_reward_rate = nft_reward_rate;
+        pool.total_staked = 0;
+        pool.reward_a_rate = 0;
+        pool.reward_a_per_token_stored = 0;
+        pool.last_update_time = 0;
+        pool.period_finish = 0;
+        pool.extra_rewards = [ExtraReward::default(); MAX_EXTRA_REWARDS];
+        pool.slash_index = SLASH_INDEX_PRECISION;
+        pool.version = POOL_VERSION;
+        pool.record_activity();
+
+        Ok(())
+    }
+
+    pub fn create_user<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, CreateUser<'info>>,
+        nonce: u8,
+        proof: Vec<[u8; 32]>,
+        max_stake: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+
+        let user = &mut ctx.accounts.user.load_init()?;
+        init_user(
+            user,
+            pool,
+            ctx.accounts.pool.key(),
+            ctx.accounts.owner.key(),
+            nonce,
+        );
+
+        if pool.has_allowlist() {
+            let leaf = allowlist_leaf(ctx.accounts.owner.key, max_stake);
+            if !verify_allowlist_proof(&proof, pool.allowlist_root, leaf) {
+                return Err(ErrorCode::InvalidAllowlistProof.into());
+            }
+            user.allowlist_cap = max_stake;
         }
-        let default_pubkey = Pubkey::default();
-        if let Some(idx) = funders.iter().position(|x| *x == default_pubkey) {
-            funders[idx] = funder_to_add;
-        } else {
-            return Err(ErrorCode::MaxFunders.into());
+
+        //an optional writable referrer user in the same pool, linked for good
+        if let Some(referrer_info) = ctx.remaining_accounts.first() {
+            let referrer_loader: AccountLoader<User> = AccountLoader::try_from(referrer_info)?;
+            let referrer = &mut referrer_loader.load_mut()?;
+            if referrer.pool != ctx.accounts.pool.key() {
+                return Err(ErrorCode::InvalidReferrer.into());
+            }
+            referrer.referral_count = referrer.referral_count.checked_add(1).unwrap();
+            user.referrer = referrer_info.key();
         }
+
         Ok(())
     }
 
-    pub fn deauthorize_funder(ctx: Context<FunderChange>, funder_to_remove: Pubkey) -> Result<()> {
-        if funder_to_remove == ctx.accounts.pool.authority.key() {
-            return Err(ErrorCode::CannotDeauthorizePoolAuthority.into());
+    pub fn set_referral_config(ctx: Context<SetPoolConfig>, referral_bps: u64, referral_payout_cap: u64) -> Result<()> {
+        if referral_bps > 10_000 {
+            return Err(ErrorCode::InvalidReferralBps.into());
         }
-        let funders = &mut ctx.accounts.pool.funders;
-        if let Some(idx) = funders.iter().position(|x| *x == funder_to_remove) {
-            funders[idx] = Pubkey::default();
-        } else {
-            return Err(ErrorCode::CannotDeauthorizeMissingAuthority.into());
+
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        pool.referral_bps = referral_bps;
+        pool.referral_payout_cap = referral_payout_cap;
+
+        Ok(())
+    }
+
+    pub fn fund_referral_reserve(ctx: Context<Fund>, amount: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+
+        //held in the reward A vault but only ever credited to referrers
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.from_a.to_account_info(),
+                to: ctx.accounts.reward_a_vault.to_account_info(),
+                authority: ctx.accounts.funder.to_account_info(),
+            },
+        );
+        token::transfer(cpi_ctx, amount)?;
+        pool.referral_reserve = pool.referral_reserve.checked_add(amount).unwrap();
+
+        Ok(())
+    }
+
+    pub fn claim_referral_rewards(ctx: Context<ClaimReferralRewards>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        let referee = &mut ctx.accounts.referee.load_mut()?;
+        update_user_rewards(pool, referee).unwrap();
+
+        //whatever the cap or the reserve holds back stays pending on the referee
+        let mut amount = referee.referral_pending.min(pool.referral_reserve);
+        if pool.referral_payout_cap > 0 {
+            amount = amount.min(pool.referral_payout_cap.saturating_sub(pool.total_referral_paid));
         }
+        referee.referral_pending = referee.referral_pending.checked_sub(amount).unwrap();
+        pool.referral_reserve = pool.referral_reserve.checked_sub(amount).unwrap();
+        pool.total_referral_paid = pool.total_referral_paid.checked_add(amount).unwrap();
+
+        //credited as reward A out of the reserve, the referrer claims it from the reward A vault as usual
+        let referrer = &mut ctx.accounts.referrer.load_mut()?;
+        update_user_rewards(pool, referrer).unwrap();
+        referrer.reward_a = referrer.reward_a.checked_add(amount).unwrap();
+        pool.reward_a_owed = pool.reward_a_owed.checked_add(amount).unwrap();
+        referrer.referral_earned = referrer.referral_earned.checked_add(amount).unwrap();
+
         Ok(())
     }
 
-    pub fn fund_staking(ctx: Context<FundStaking>, amount: u64) -> Result<()> {
+    pub fn pause(ctx: Context<Pause>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        pool.paused = true as u8;
 
-        if amount > 0 {
+        Ok(())
+    }
+
+    pub fn unpause(ctx: Context<Unpause>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        pool.paused = false as u8;
+
+        Ok(())
+    }
+
+    pub fn stake(ctx: Context<Stake>, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        if pool.is_paused() {
+            return Err(ErrorCode::PoolPaused.into());
+        }
+
+        let user = &mut ctx.accounts.user.load_mut()?;
+        update_user_rewards(pool, user).unwrap();
+
+        //new stake has held for no time, so it dilutes the loyalty clock
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        dilute_loyalty(user, amount, current_time);
+        user.balance_staked = user.balance_staked.checked_add(amount).unwrap();
+        pool.total_staked = pool.total_staked.checked_add(amount).unwrap();
+        pool.check_deposit_caps(user)?;
+
+        //refresh the reward b rate for the new balance
+        update_user_rewards(pool, user).unwrap();
+
+
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        user.stake_time = current_time;
+        //new stake waits out a fresh cooldown before it can leave
+        user.cooldown_start = 0;
+
+        // Transfer tokens into the stake vault.
+        {
             let cpi_ctx = CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
                 token::Transfer {
-                    from: ctx.accounts.from.to_account_info(),
+                    from: ctx.accounts.stake_from_account.to_account_info(),
                     to: ctx.accounts.staking_vault.to_account_info(),
-                    authority: ctx.accounts.funder.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(), //todo use user account as signer
                 },
             );
-
             token::transfer(cpi_ctx, amount)?;
         }
 
         Ok(())
     }
 
-    pub fn fund(ctx: Context<Fund>, amount_a: u64) -> Result<()> {
+    pub fn stake_for(ctx: Context<StakeFor>, nonce: u8, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
 
-        // Transfer reward A tokens into the A vault.
-        if amount_a > 0 {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        if pool.is_paused() {
+            return Err(ErrorCode::PoolPaused.into());
+        }
+
+        //a freshly created user account is still zeroed
+        let user = &mut load_init_if_needed(&ctx.accounts.user)?;
+        let is_new_user = user.owner == Pubkey::default();
+        if is_new_user {
+            //allowlisted wallets join through create_user with a proof
+            if pool.has_allowlist() {
+                return Err(ErrorCode::AllowlistProofRequired.into());
+            }
+            init_user(
+                user,
+                pool,
+                ctx.accounts.pool.key(),
+                ctx.accounts.beneficiary.key(),
+                nonce,
+            );
+        }
+
+        update_user_rewards(pool, user).unwrap();
+
+        //new stake has held for no time, so it dilutes the loyalty clock
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        dilute_loyalty(user, amount, current_time);
+        user.balance_staked = user.balance_staked.checked_add(amount).unwrap();
+        pool.total_staked = pool.total_staked.checked_add(amount).unwrap();
+        pool.check_deposit_caps(user)?;
+
+        //refresh the reward b rate for the new balance
+        update_user_rewards(pool, user).unwrap();
+
+        //only the beneficiary can agree to push their lock out, and only if the pool allows it
+        if pool.stake_for_resets_lock() && ctx.accounts.beneficiary.is_signer {
+            let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+            user.stake_time = current_time;
+        }
+        //likewise only the beneficiary can restart their own cooldown
+        if ctx.accounts.beneficiary.is_signer {
+            user.cooldown_start = 0;
+        }
+
+        // Transfer tokens into the stake vault.
+        {
             let cpi_ctx = CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
                 token::Transfer {
-                    from: ctx.accounts.from_a.to_account_info(),
-                    to: ctx.accounts.reward_a_vault.to_account_info(),
-                    authority: ctx.accounts.funder.to_account_info(),
+                    from: ctx.accounts.stake_from_account.to_account_info(),
+                    to: ctx.accounts.staking_vault.to_account_info(),
+                    authority: ctx.accounts.payer.to_account_info(),
                 },
             );
-
-            token::transfer(cpi_ctx, amount_a)?;
+            token::transfer(cpi_ctx, amount)?;
         }
 
         Ok(())
     }
 
-    pub fn claim(ctx: Context<ClaimReward>) -> Result<()> {
-        let user_opt = Some(&mut ctx.accounts.user);
-        update_rewards(
-            user_opt,
-        )
-        .unwrap();
+    pub fn set_stake_for_resets_lock(ctx: Context<SetPoolConfig>, resets_lock: bool) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        pool.stake_for_resets_lock = resets_lock as u8;
 
-        let seeds = &[
-            ctx.accounts.pool.to_account_info().key.as_ref(),
-            &[ctx.accounts.pool.nonce],
-        ];
-        let pool_signer = &[&seeds[..]];
+        Ok(())
+    }
 
-        if ctx.accounts.user.reward_a > 0 {
-            let mut reward_amount = ctx.accounts.user.reward_a;
-            let vault_balance = ctx.accounts.reward_a_vault.amount;
+    pub fn transfer_position(ctx: Context<TransferPosition>, nonce: u8, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
 
-            ctx.accounts.user.reward_a = 0;
-            if vault_balance < reward_amount {
-                reward_amount = vault_balance;
-            }
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        if pool.is_paused() {
+            return Err(ErrorCode::PoolPaused.into());
+        }
+        if pool.kind() != PoolKind::Fungible {
+            return Err(ErrorCode::WrongPoolKind.into());
+        }
+        if !pool.transfers_enabled() {
+            return Err(ErrorCode::PositionTransfersDisabled.into());
+        }
 
-            if reward_amount > 0 {
-                let cpi_ctx = CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    token::Transfer {
-                        from: ctx.accounts.reward_a_vault.to_account_info(),
-                        to: ctx.accounts.reward_a_account.to_account_info(),
-                        authority: ctx.accounts.pool_signer.to_account_info(),
-                    },
-                    pool_signer,
-                );
-                token::transfer(cpi_ctx, reward_amount)?;
+        let from_user = &mut ctx.accounts.from_user.load_mut()?;
+        check_no_outstanding_votes(pool, &from_user.owner, ctx.remaining_accounts)?;
+        let to_user = &mut load_init_if_needed(&ctx.accounts.to_user)?;
+        let is_new_user = to_user.owner == Pubkey::default();
+        if is_new_user {
+            if pool.has_allowlist() {
+                return Err(ErrorCode::AllowlistProofRequired.into());
             }
+            init_user(
+                to_user,
+                pool,
+                ctx.accounts.pool.key(),
+                ctx.accounts.recipient.key(),
+                nonce,
+            );
         }
 
-        if ctx.accounts.user.reward_b > 0 {
-            let mut reward_amount = ctx.accounts.user.reward_b;
-            let vault_balance = ctx.accounts.staking_vault.amount;
+        //settle both sides at their current rates before balances move
+        update_user_rewards(pool, from_user).unwrap();
+        update_user_rewards(pool, to_user).unwrap();
 
-            ctx.accounts.user.reward_b = 0;
-            if vault_balance < reward_amount {
-                reward_amount = vault_balance;
-            }
+        //checked after pending slashes are applied
+        if from_user.balance_staked < amount {
+            return Err(ErrorCode::InsufficientFundTransfer.into());
+        }
 
-            if reward_amount > 0 {
-                let cpi_ctx = CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    token::Transfer {
-                        from: ctx.accounts.staking_vault.to_account_info(),
-                        to: ctx.accounts.reward_b_account.to_account_info(),
-                        authority: ctx.accounts.pool_signer.to_account_info(),
-                    },
-                    pool_signer,
-                );
-                token::transfer(cpi_ctx, reward_amount)?;
-            }
+        //the recipient did not sign, so its lock and cooldown never move, an empty one takes the sender's
+        if to_user.balance_staked == 0 {
+            to_user.stake_time = from_user.stake_time;
+            to_user.cooldown_start = from_user.cooldown_start;
+        } else if outlasts_recipient(pool, from_user, to_user) {
+            return Err(ErrorCode::TransferOutlastsRecipient.into());
         }
 
+        //moving stake out counts as a withdrawal for the sender's loyalty
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        reset_loyalty(from_user, amount, current_time);
+        dilute_loyalty(to_user, amount, current_time);
+        from_user.balance_staked = from_user.balance_staked.checked_sub(amount).unwrap();
+        to_user.balance_staked = to_user.balance_staked.checked_add(amount).unwrap();
+        //the recipient's caps hold as if it had staked the amount itself
+        pool.check_deposit_caps(to_user)?;
+
+        //refresh rates for the new balances
+        update_user_rewards(pool, from_user).unwrap();
+        update_user_rewards(pool, to_user).unwrap();
+
         Ok(())
     }
 
-    pub fn close_user(ctx: Context<CloseUser>) -> Result<()> {
-        let pool = &mut ctx.accounts.pool;
-        pool.user_stake_count = pool.user_stake_count.checked_sub(1).unwrap();
+    pub fn set_transfers_enabled(ctx: Context<SetPoolConfig>, enabled: bool) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        pool.transfers_enabled = enabled as u8;
+
         Ok(())
     }
 
-    pub fn close_pool<'info>(ctx: Context<ClosePool>) -> Result<()> {
-        let pool = &ctx.accounts.pool;
-        
-        let signer_seeds = &[pool.to_account_info().key.as_ref(), &[ctx.accounts.pool.nonce]];
-        
-        //instead of closing these vaults, we could technically just 
-        //set_authority on them. it's not very ata clean, but it'd work
-        //if size of tx is an issue, thats an approach
+    pub fn set_allowlist_root(ctx: Context<SetPoolConfig>, root: [u8; 32]) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        //existing users keep their accounts and caps, only new joins are checked
+        pool.allowlist_root = root;
 
-        //close staking vault
-        let ix = spl_token::instruction::transfer(
-            &spl_token::ID,
-            ctx.accounts.staking_vault.to_account_info().key,
-            ctx.accounts.staking_refundee.to_account_info().key,
-            ctx.accounts.pool_signer.key,
-            &[ctx.accounts.pool_signer.key],
-            ctx.accounts.staking_vault.amount,
-        )?;
-        solana_program::program::invoke_signed(
-            &ix,
-            &[
-                ctx.accounts.token_program.to_account_info(),
-                ctx.accounts.staking_vault.to_account_info(),
-                ctx.accounts.staking_refundee.to_account_info(),
-                ctx.accounts.pool_signer.to_account_info(),
-            ],
-            &[signer_seeds],
-        )?;
-        let ix = spl_token::instruction::close_account(
-            &spl_token::ID,
-            ctx.accounts.staking_vault.to_account_info().key,
-            ctx.accounts.refundee.key,
-            ctx.accounts.pool_signer.key,
-            &[ctx.accounts.pool_signer.key],
-        )?;
-        solana_program::program::invoke_signed(
-            &ix,
-            &[
-                ctx.accounts.token_program.to_account_info(),
-                ctx.accounts.staking_vault.to_account_info(),
-                ctx.accounts.refundee.to_account_info(),
-                ctx.accounts.pool_signer.to_account_info(),
-            ],
-            &[signer_seeds],
-        )?;
-        
-        //close token a vault
-        let ix = spl_token::instruction::transfer(
-            &spl_token::ID,
-            ctx.accounts.reward_a_vault.to_account_info().key,
-            ctx.accounts.reward_a_refundee.to_account_info().key,
-            ctx.accounts.pool_signer.key,
-            &[ctx.accounts.pool_signer.key],
-            ctx.accounts.reward_a_vault.amount,
-        )?;
-        solana_program::program::invoke_signed(
-            &ix,
-            &[
-                ctx.accounts.token_program.to_account_info(),
-                ctx.accounts.reward_a_vault.to_account_info(),
-                ctx.accounts.reward_a_refundee.to_account_info(),
-                ctx.accounts.pool_signer.to_account_info(),
-            ],
-            &[signer_seeds],
-        )?;
-        let ix = spl_token::instruction::close_account(
-            &spl_token::ID,
-            ctx.accounts.reward_a_vault.to_account_info().key,
-            ctx.accounts.refundee.key,
-            ctx.accounts.pool_signer.key,
-            &[ctx.accounts.pool_signer.key],
-        )?;
-        solana_program::program::invoke_signed(
-            &ix,
-            &[
+        Ok(())
+    }
+
+    pub fn set_boost_pool(ctx: Context<SetPoolConfig>, boost_pool: Pubkey) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        //working balances follow on each user's next update or poke
+        pool.boost_pool = boost_pool;
+
+        Ok(())
+    }
+
+    pub fn poke_boost(ctx: Context<PokeBoost>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        let user = &mut ctx.accounts.user.load_mut()?;
+        update_user_rewards(pool, user).unwrap();
+
+        //a missing boost user just means nothing staked in the boost pool
+        let boost_pool = ctx.accounts.boost_pool.load()?;
+        let boost_user_info = ctx.accounts.boost_user.to_account_info();
+        let boost_balance = if *boost_user_info.owner == ID && !boost_user_info.data_is_empty() {
+            let boost_user: AccountLoader<User> = AccountLoader::try_from(&boost_user_info)?;
+            //slashes the boost user has not picked up yet still count against it
+            let balance = boost_user.load()?.current_balance_staked(&boost_pool);
+            balance
+        } else {
+            0
+        };
+        user.boost_share = if boost_pool.total_staked == 0 {
+            0
+        } else {
+            (boost_balance as u128)
+                .checked_mul(BOOST_SHARE_PRECISION as u128)
+                .unwrap()
+                .checked_div(boost_pool.total_staked as u128)
+                .unwrap()
+                .try_into()
+                .unwrap()
+        };
+
+        //refresh the working balance for the new share
+        update_user_rewards(pool, user).unwrap();
+
+        Ok(())
+    }
+
+    pub fn set_loyalty_schedule(
+        ctx: Context<SetPoolConfig>,
+        step_duration: u64,
+        step_bps: u64,
+        max_bps: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        //working balances follow on each user's next update
+        pool.loyalty_step_duration = step_duration;
+        pool.loyalty_step_bps = step_bps;
+        pool.loyalty_max_bps = max_bps;
+
+        Ok(())
+    }
+
+    pub fn set_tiers(
+        ctx: Context<SetPoolConfig>,
+        thresholds: [u64; TIER_COUNT],
+        reward_b_bps: [u64; TIER_COUNT],
+    ) -> Result<()> {
+        //all zero turns tiers off, otherwise every tier needs a higher threshold than the last
+        let disabled = thresholds.iter().all(|threshold| *threshold == 0);
+        if !disabled && (thresholds[0] == 0 || thresholds.windows(2).any(|pair| pair[0] >= pair[1])) {
+            return Err(ErrorCode::InvalidTierThresholds.into());
+        }
+        if reward_b_bps.iter().any(|bps| *bps > MAX_TIER_REWARD_B_BPS) {
+            return Err(ErrorCode::InvalidTierRewardRate.into());
+        }
+
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        //users move tiers on their next update or refresh_tier
+        pool.tier_thresholds = thresholds;
+        pool.tier_reward_b_bps = reward_b_bps;
+
+        Ok(())
+    }
+
+    pub fn refresh_tier(ctx: Context<RefreshTier>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        let user = &mut ctx.accounts.user.load_mut()?;
+        update_user_rewards(pool, user).unwrap();
+
+        Ok(())
+    }
+
+    pub fn set_deposit_caps(ctx: Context<SetPoolConfig>, max_total_staked: u64, max_per_user: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        //lowering a cap below current stakes only blocks new deposits
+        pool.max_total_staked = max_total_staked;
+        pool.max_per_user = max_per_user;
+
+        Ok(())
+    }
+
+    pub fn unstake(ctx: Context<Stake>, spt_amount: u64) -> Result<()> {
+        if spt_amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        let user = &mut ctx.accounts.user.load_mut()?;
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        if current_time < unlock_time(pool, user.stake_time) {
+            return Err(ErrorCode::NotEnoughUnstakePeriod.into());
+        }
+        pool.check_cooldown(user, current_time)?;
+        check_no_outstanding_votes(pool, &user.owner, ctx.remaining_accounts)?;
+        update_user_rewards(pool, user).unwrap();
+
+        //checked after pending slashes are applied
+        if user.balance_staked < spt_amount {
+            return Err(ErrorCode::InsufficientFundUnstake.into());
+        }
+
+        reset_loyalty(user, spt_amount, current_time);
+        user.balance_staked = user.balance_staked.checked_sub(spt_amount).unwrap();
+        pool.total_staked = pool.total_staked.checked_sub(spt_amount).unwrap();
+
+        //refresh the reward b rate for the new balance
+        update_user_rewards(pool, user).unwrap();
+
+        // Transfer tokens from the pool vault to user vault.
+        {
+            let seeds = &[
+                ctx.accounts.pool.to_account_info().key.as_ref(),
+                &[pool.nonce],
+            ];
+            let pool_signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                ctx.accounts.reward_a_vault.to_account_info(),
-                ctx.accounts.refundee.to_account_info(),
-                ctx.accounts.pool_signer.to_account_info(),
-            ],
-            &[signer_seeds],
-        )?;
-        
+                token::Transfer {
+                    from: ctx.accounts.staking_vault.to_account_info(),
+                    to: ctx.accounts.stake_from_account.to_account_info(),
+                    authority: ctx.accounts.pool_signer.to_account_info(),
+                },
+                pool_signer,
+            );
+            token::transfer(cpi_ctx, spt_amount.try_into().unwrap())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn withdraw(ctx: Context<Withdraw>, spt_amount: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+
+        // Transfer tokens from the pool vault to user vault.
+        {
+            let seeds = &[
+                ctx.accounts.pool.to_account_info().key.as_ref(),
+                &[pool.nonce],
+            ];
+            let pool_signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.staking_vault.to_account_info(),
+                    to: ctx.accounts.withdraw_account.to_account_info(),
+                    authority: ctx.accounts.pool_signer.to_account_info(),
+                },
+                pool_signer,
+            );
+            token::transfer(cpi_ctx, spt_amount.try_into().unwrap())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn authorize_funder(ctx: Context<FunderChange>, funder_to_add: Pubkey) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        if funder_to_add == pool.authority {
+            return Err(ErrorCode::FunderAlreadyAuthorized.into());
+        }
+        let funders = &mut pool.funders;
+        if funders.iter().any(|x| *x == funder_to_add) {
+            return Err(ErrorCode::FunderAlreadyAuthorized.into());
+        }
+        let default_pubkey = Pubkey::default();
+        if let Some(idx) = funders.iter().position(|x| *x == default_pubkey) {
+            funders[idx] = funder_to_add;
+        } else {
+            return Err(ErrorCode::MaxFunders.into());
+        }
+        Ok(())
+    }
+
+    pub fn deauthorize_funder(ctx: Context<FunderChange>, funder_to_remove: Pubkey) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        if funder_to_remove == pool.authority {
+            return Err(ErrorCode::CannotDeauthorizePoolAuthority.into());
+        }
+        let funders = &mut pool.funders;
+        if let Some(idx) = funders.iter().position(|x| *x == funder_to_remove) {
+            funders[idx] = Pubkey::default();
+        } else {
+            return Err(ErrorCode::CannotDeauthorizeMissingAuthority.into());
+        }
+        Ok(())
+    }
+
+    pub fn fund_staking(ctx: Context<FundStaking>, amount: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+
+        if amount > 0 {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.from.to_account_info(),
+                    to: ctx.accounts.staking_vault.to_account_info(),
+                    authority: ctx.accounts.funder.to_account_info(),
+                },
+            );
+
+            token::transfer(cpi_ctx, amount)?;
+            pool.total_funded_b = pool.total_funded_b.checked_add(amount).unwrap();
+        }
+
+        Ok(())
+    }
+
+    pub fn fund(ctx: Context<Fund>, amount_a: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+
+        // Transfer reward A tokens into the A vault.
+        if amount_a > 0 {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.from_a.to_account_info(),
+                    to: ctx.accounts.reward_a_vault.to_account_info(),
+                    authority: ctx.accounts.funder.to_account_info(),
+                },
+            );
+
+            token::transfer(cpi_ctx, amount_a)?;
+            pool.total_funded_a = pool.total_funded_a.checked_add(amount_a).unwrap();
+        }
+
+        Ok(())
+    }
+
+    pub fn notify_reward_amount(ctx: Context<Fund>, amount_a: u64, duration: u64) -> Result<()> {
+        if duration < MIN_REWARD_DURATION {
+            return Err(ErrorCode::DurationTooShort.into());
+        }
+
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        update_rewards(
+            pool,
+            None,
+        )
+        .unwrap();
+
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+
+        //whatever is left of an unfinished campaign rolls into the new one
+        let mut total_amount = amount_a;
+        if current_time < pool.period_finish {
+            let leftover = pool.period_finish
+                .checked_sub(current_time)
+                .unwrap()
+                .checked_mul(pool.reward_a_rate)
+                .unwrap();
+            total_amount = total_amount.checked_add(leftover).unwrap();
+        }
+
+        pool.reward_a_rate = total_amount.checked_div(duration).unwrap();
+        pool.last_update_time = current_time;
+        pool.period_finish = current_time.checked_add(duration).unwrap();
+
+        //the vault has to cover the whole campaign on top of what stakers are already owed
+        let balance = pool.reward_a_available(ctx.accounts.reward_a_vault.amount.checked_add(amount_a).unwrap());
+        check_reward_funded(pool.reward_a_rate, balance, pool.reward_a_owed, duration)?;
+
+        // Transfer reward A tokens into the A vault.
+        if amount_a > 0 {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.from_a.to_account_info(),
+                    to: ctx.accounts.reward_a_vault.to_account_info(),
+                    authority: ctx.accounts.funder.to_account_info(),
+                },
+            );
+
+            token::transfer(cpi_ctx, amount_a)?;
+            pool.total_funded_a = pool.total_funded_a.checked_add(amount_a).unwrap();
+        }
+
+        Ok(())
+    }
+
+    pub fn add_reward(ctx: Context<AddReward>) -> Result<()> {
+        let reward_mint = ctx.accounts.reward_mint.key();
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        if reward_mint == pool.staking_mint
+            || reward_mint == pool.reward_a_mint
+            || pool.extra_rewards.iter().any(|x| x.mint == reward_mint) {
+            return Err(ErrorCode::RewardAlreadyAdded.into());
+        }
+
+        let default_pubkey = Pubkey::default();
+        if let Some(idx) = pool.extra_rewards.iter().position(|x| x.mint == default_pubkey) {
+            pool.extra_rewards[idx] = ExtraReward {
+                mint: reward_mint,
+                vault: ctx.accounts.reward_vault.key(),
+                ..ExtraReward::default()
+            };
+        } else {
+            return Err(ErrorCode::MaxRewards.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn notify_extra_reward_amount(
+        ctx: Context<FundExtraReward>,
+        index: u8,
+        amount: u64,
+        duration: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        let index = index as usize;
+        if index >= MAX_EXTRA_REWARDS
+            || pool.extra_rewards[index].mint == Pubkey::default() {
+            return Err(ErrorCode::RewardNotFound.into());
+        }
+        if pool.extra_rewards[index].vault != ctx.accounts.reward_vault.key() {
+            return Err(ErrorCode::InvalidRewardVault.into());
+        }
+        if duration < MIN_REWARD_DURATION {
+            return Err(ErrorCode::DurationTooShort.into());
+        }
+
+        update_rewards(
+            pool,
+            None,
+        )
+        .unwrap();
+
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        let reward = &mut pool.extra_rewards[index];
+
+        //whatever is left of an unfinished campaign rolls into the new one
+        let mut total_amount = amount;
+        if current_time < reward.period_finish {
+            let leftover = reward.period_finish
+                .checked_sub(current_time)
+                .unwrap()
+                .checked_mul(reward.reward_rate)
+                .unwrap();
+            total_amount = total_amount.checked_add(leftover).unwrap();
+        }
+
+        reward.reward_rate = total_amount.checked_div(duration).unwrap();
+        reward.last_update_time = current_time;
+        reward.period_finish = current_time.checked_add(duration).unwrap();
+
+        //the vault has to cover the whole campaign on top of what stakers are already owed
+        let balance = ctx.accounts.reward_vault.amount.checked_add(amount).unwrap();
+        check_reward_funded(reward.reward_rate, balance, pool.extra_rewards_owed[index], duration)?;
+
+        if amount > 0 {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.from.to_account_info(),
+                    to: ctx.accounts.reward_vault.to_account_info(),
+                    authority: ctx.accounts.funder.to_account_info(),
+                },
+            );
+
+            token::transfer(cpi_ctx, amount)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn claim<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, ClaimReward<'info>>) -> Result<()> {
+        claim_rewards(ctx.accounts, ctx.remaining_accounts, false)
+    }
+
+    pub fn claim_instant<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, ClaimReward<'info>>) -> Result<()> {
+        claim_rewards(ctx.accounts, ctx.remaining_accounts, true)
+    }
+
+    pub fn set_vesting(
+        ctx: Context<SetPoolConfig>,
+        vesting_cliff: u64,
+        vesting_duration: u64,
+        instant_claim_haircut_bps: u64,
+    ) -> Result<()> {
+        if vesting_cliff > vesting_duration {
+            return Err(ErrorCode::InvalidVestingSchedule.into());
+        }
+        if instant_claim_haircut_bps > 10_000 {
+            return Err(ErrorCode::InvalidHaircutBps.into());
+        }
+
+        //schedules already running keep the terms they started with
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        pool.vesting_cliff = vesting_cliff;
+        pool.vesting_duration = vesting_duration;
+        pool.instant_claim_haircut_bps = instant_claim_haircut_bps;
+
+        Ok(())
+    }
+
+    pub fn set_slasher(ctx: Context<SetPoolConfig>, slasher: Pubkey, max_slash_bps: u64) -> Result<()> {
+        if max_slash_bps >= 10_000 {
+            return Err(ErrorCode::InvalidSlashBps.into());
+        }
+
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        //without a cooldown stakers could leave ahead of every slash
+        if slasher != Pubkey::default() && pool.cooldown_duration == 0 {
+            return Err(ErrorCode::SlasherRequiresCooldown.into());
+        }
+        pool.slasher = slasher;
+        pool.max_slash_bps = max_slash_bps;
+
+        Ok(())
+    }
+
+    pub fn set_cooldown(ctx: Context<SetPoolConfig>, cooldown_duration: u64, unstake_window: u64) -> Result<()> {
+        if cooldown_duration > 0 && unstake_window == 0 {
+            return Err(ErrorCode::InvalidUnstakeWindow.into());
+        }
+
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        if cooldown_duration == 0 && pool.slasher != Pubkey::default() {
+            return Err(ErrorCode::SlasherRequiresCooldown.into());
+        }
+        pool.cooldown_duration = cooldown_duration;
+        pool.unstake_window = unstake_window;
+
+        Ok(())
+    }
+
+    pub fn set_unstake_lock_period(ctx: Context<SetPoolConfig>, unstake_lock_period: u64) -> Result<()> {
+        //pools can shorten the lock, say when a cooldown guards unstakes instead
+        if unstake_lock_period > UNSTAKE_LOCK_PERIOD {
+            return Err(ErrorCode::InvalidUnstakeLockPeriod.into());
+        }
+
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        pool.unstake_lock_period = unstake_lock_period;
+
         Ok(())
     }
+
+    pub fn start_cooldown(ctx: Context<StartCooldown>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        let user = &mut ctx.accounts.user.load_mut()?;
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        user.cooldown_start = current_time;
+
+        Ok(())
+    }
+
+    pub fn start_position_cooldown(ctx: Context<StartPositionCooldown>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        let position = &mut ctx.accounts.position.load_mut()?;
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        position.cooldown_start = current_time;
+
+        Ok(())
+    }
+
+    pub fn slash(ctx: Context<Slash>, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        //settle rewards on the old supply before it shrinks
+        update_rewards(pool, None).unwrap();
+
+        let max_slash = (pool.total_staked as u128)
+            .checked_mul(pool.max_slash_bps as u128)
+            .unwrap()
+            / 10_000;
+        if amount as u128 > max_slash {
+            return Err(ErrorCode::SlashTooLarge.into());
+        }
+
+        //every stake shrinks by the same fraction, users pick it up on their next update
+        let remaining = pool.total_staked.checked_sub(amount).unwrap();
+        pool.slash_index = apply_slash_index(pool.slash_index, pool.total_staked, remaining);
+        pool.working_supply = apply_slash_index(pool.working_supply, pool.total_staked, remaining);
+        pool.total_staked = remaining;
+        pool.total_slashed = pool.total_slashed.checked_add(amount).unwrap();
+
+        {
+            let seeds = &[
+                ctx.accounts.pool.to_account_info().key.as_ref(),
+                &[pool.nonce],
+            ];
+            let pool_signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.staking_vault.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: ctx.accounts.pool_signer.to_account_info(),
+                },
+                pool_signer,
+            );
+            token::transfer(cpi_ctx, amount)?;
+        }
+
+        let clock = clock::Clock::get().unwrap();
+        emit!(Slashed {
+            pool: ctx.accounts.pool.key(),
+            slasher: ctx.accounts.slasher.key(),
+            destination: ctx.accounts.destination.key(),
+            amount,
+            total_staked: pool.total_staked,
+            slash_index: pool.slash_index,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn release_vested(ctx: Context<ReleaseVested>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        let user = &mut ctx.accounts.user.load_mut()?;
+
+        let clock = clock::Clock::get().unwrap();
+        let amount = std::cmp::min(
+            releasable_amount(user, clock.unix_timestamp as u64),
+            pool.reward_a_available(ctx.accounts.reward_a_vault.amount),
+        );
+        //earlier schedules first, the rest comes off the current one
+        let from_unlocked = std::cmp::min(amount, user.vesting_unlocked);
+        user.vesting_unlocked = user.vesting_unlocked.checked_sub(from_unlocked).unwrap();
+        user.vesting_released = user
+            .vesting_released
+            .checked_add(amount.checked_sub(from_unlocked).unwrap())
+            .unwrap();
+
+        if amount > 0 {
+            let seeds = &[
+                ctx.accounts.pool.to_account_info().key.as_ref(),
+                &[pool.nonce],
+            ];
+            let pool_signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.reward_a_vault.to_account_info(),
+                    to: ctx.accounts.reward_a_account.to_account_info(),
+                    authority: ctx.accounts.pool_signer.to_account_info(),
+                },
+                pool_signer,
+            );
+            token::transfer(cpi_ctx, amount)?;
+            pool.total_rewards_a_paid = pool.total_rewards_a_paid.checked_add(amount).unwrap();
+        }
+
+        Ok(())
+    }
+
+    pub fn close_user(ctx: Context<CloseUser>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        pool.user_stake_count = pool.user_stake_count.checked_sub(1).unwrap();
+        Ok(())
+    }
+
+    pub fn migrate_pool(ctx: Context<MigratePool>) -> Result<()> {
+        let info = ctx.accounts.pool.to_account_info();
+        realloc_account(
+            &info,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            Pool::space(),
+        )?;
+
+        let loader: AccountLoader<Pool> = AccountLoader::try_from(&info)?;
+        let pool = &mut loader.load_mut()?;
+        //backfill every layout step the account has missed, oldest first
+        //with the defaults init_pool writes, legacy pools are fungible and allow transfers
+        if pool.version < 1 {
+            pool.stake_for_resets_lock = false as u8;
+            pool.transfers_enabled = true as u8;
+            pool.kind = PoolKind::Fungible as u8;
+            pool.version = 1;
+        }
+        //stats start counting from the upgrade, legacy stake is added back as its users migrate
+        if pool.version < 2 {
+            pool.version = 2;
+        }
+        //governance stays off until the authority picks a realm
+        if pool.version < 3 {
+            pool.version = 3;
+        }
+        backfill_pool_reward_fields(pool);
+        //the default lock stays until the authority shortens it
+        if pool.version < 7 {
+            pool.version = 7;
+        }
+
+        Ok(())
+    }
+
+    pub fn migrate_user(ctx: Context<MigrateUser>) -> Result<()> {
+        let info = ctx.accounts.user.to_account_info();
+        realloc_account(
+            &info,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            User::space(),
+        )?;
+
+        let loader: AccountLoader<User> = AccountLoader::try_from(&info)?;
+        let user = &mut loader.load_mut()?;
+        if user.pool != ctx.accounts.pool.key() {
+            return Err(ErrorCode::UserPoolMismatch.into());
+        }
+        //backfill every layout step the account has missed, oldest first
+        if user.version < 1 {
+            //stake from before pool stats, counted as working like backfill_user_reward_fields does
+            let pool = &mut ctx.accounts.pool.load_mut()?;
+            pool.total_staked = pool.total_staked.checked_add(user.balance_staked).unwrap();
+            pool.working_supply = pool.working_supply.checked_add(user.balance_staked).unwrap();
+            user.version = 1;
+        }
+        backfill_user_reward_fields(user);
+
+        Ok(())
+    }
+
+    pub fn stake_position(ctx: Context<StakePosition>, nonce: u8, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        if pool.is_paused() {
+            return Err(ErrorCode::PoolPaused.into());
+        }
+
+        //position nfts can change hands, which would sidestep the allowlist
+        if pool.has_allowlist() {
+            return Err(ErrorCode::AllowlistProofRequired.into());
+        }
+
+        //position users are owned by their nft mint, authority follows whoever holds the nft
+        let position = &mut ctx.accounts.position.load_init()?;
+        init_user(
+            position,
+            pool,
+            ctx.accounts.pool.key(),
+            ctx.accounts.position_mint.key(),
+            nonce,
+        );
+
+        update_user_rewards(pool, position).unwrap();
+
+        position.balance_staked = amount;
+        pool.total_staked = pool.total_staked.checked_add(amount).unwrap();
+        pool.check_deposit_caps(position)?;
+
+        //refresh the reward b rate for the new balance
+        update_user_rewards(pool, position).unwrap();
+
+        let seeds = &[
+            ctx.accounts.pool.to_account_info().key.as_ref(),
+            &[pool.nonce],
+        ];
+        let pool_signer = &[&seeds[..]];
+
+        // Mint the position nft to the staker.
+        {
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::MintTo {
+                    mint: ctx.accounts.position_mint.to_account_info(),
+                    to: ctx.accounts.position_token_account.to_account_info(),
+                    authority: ctx.accounts.pool_signer.to_account_info(),
+                },
+                pool_signer,
+            );
+            token::mint_to(cpi_ctx, 1)?;
+        }
+
+        // Transfer tokens into the stake vault.
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.stake_from_account.to_account_info(),
+                    to: ctx.accounts.staking_vault.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            );
+            token::transfer(cpi_ctx, amount)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn create_position_metadata(
+        ctx: Context<CreatePositionMetadata>,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        let seeds = &[
+            ctx.accounts.pool.to_account_info().key.as_ref(),
+            &[ctx.accounts.pool.load()?.nonce],
+        ];
+        let pool_signer = &[&seeds[..]];
+
+        let ix = metadata::create_metadata_accounts_v3(
+            ctx.accounts.metadata.key(),
+            ctx.accounts.position_mint.key(),
+            ctx.accounts.pool_signer.key(),
+            ctx.accounts.payer.key(),
+            ctx.accounts.pool_signer.key(),
+            metadata::DataV2 {
+                name,
+                symbol,
+                uri,
+                seller_fee_basis_points: 0,
+                creators: None,
+                collection: None,
+                uses: None,
+            },
+        );
+        solana_program::program::invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.metadata_program.to_account_info(),
+                ctx.accounts.metadata.to_account_info(),
+                ctx.accounts.position_mint.to_account_info(),
+                ctx.accounts.pool_signer.to_account_info(),
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            pool_signer,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn update_position_metadata(
+        ctx: Context<UpdatePositionMetadata>,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        let seeds = &[
+            ctx.accounts.pool.to_account_info().key.as_ref(),
+            &[ctx.accounts.pool.load()?.nonce],
+        ];
+        let pool_signer = &[&seeds[..]];
+
+        let ix = metadata::update_metadata_accounts_v2(
+            ctx.accounts.metadata.key(),
+            ctx.accounts.pool_signer.key(),
+            metadata::DataV2 {
+                name,
+                symbol,
+                uri,
+                seller_fee_basis_points: 0,
+                creators: None,
+                collection: None,
+                uses: None,
+            },
+        );
+        solana_program::program::invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.metadata_program.to_account_info(),
+                ctx.accounts.metadata.to_account_info(),
+                ctx.accounts.pool_signer.to_account_info(),
+            ],
+            pool_signer,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn unstake_position(ctx: Context<UnstakePosition>, spt_amount: u64) -> Result<()> {
+        if spt_amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        let position = &mut ctx.accounts.position.load_mut()?;
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        if current_time < unlock_time(pool, position.stake_time) {
+            return Err(ErrorCode::NotEnoughUnstakePeriod.into());
+        }
+        pool.check_cooldown(position, current_time)?;
+        check_no_outstanding_votes(pool, &position.owner, ctx.remaining_accounts)?;
+        update_user_rewards(pool, position).unwrap();
+
+        //checked after pending slashes are applied
+        if position.balance_staked < spt_amount {
+            return Err(ErrorCode::InsufficientFundUnstake.into());
+        }
+
+        reset_loyalty(position, spt_amount, current_time);
+        position.balance_staked = position.balance_staked.checked_sub(spt_amount).unwrap();
+        pool.total_staked = pool.total_staked.checked_sub(spt_amount).unwrap();
+
+        //refresh the reward b rate for the new balance
+        update_user_rewards(pool, position).unwrap();
+
+        // Transfer tokens from the pool vault to the holder.
+        {
+            let seeds = &[
+                ctx.accounts.pool.to_account_info().key.as_ref(),
+                &[pool.nonce],
+            ];
+            let pool_signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.staking_vault.to_account_info(),
+                    to: ctx.accounts.stake_to_account.to_account_info(),
+                    authority: ctx.accounts.pool_signer.to_account_info(),
+                },
+                pool_signer,
+            );
+            token::transfer(cpi_ctx, spt_amount)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn claim_position<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, ClaimPosition<'info>>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        //vesting schedules are released by their owner, which a position cannot sign as
+        if pool.has_vesting() {
+            return Err(ErrorCode::PositionVestingUnsupported.into());
+        }
+        pool.record_activity();
+        let position = &mut ctx.accounts.position.load_mut()?;
+        update_user_rewards(pool, position).unwrap();
+
+        let seeds = &[
+            ctx.accounts.pool.to_account_info().key.as_ref(),
+            &[pool.nonce],
+        ];
+        let pool_signer = &[&seeds[..]];
+
+        pay_rewards(
+            pool,
+            position,
+            ctx.accounts.holder.key(),
+            &ClaimPayout {
+                reward_a_vault: &ctx.accounts.reward_a_vault,
+                reward_a_account: &ctx.accounts.reward_a_account,
+                staking_vault: &ctx.accounts.staking_vault,
+                reward_b_account: &ctx.accounts.reward_b_account,
+                pool_signer: ctx.accounts.pool_signer.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+            pool_signer,
+            ctx.remaining_accounts,
+        )
+    }
+
+    pub fn close_position(ctx: Context<ClosePosition>) -> Result<()> {
+        // Burn the position nft.
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Burn {
+                    mint: ctx.accounts.position_mint.to_account_info(),
+                    to: ctx.accounts.position_token_account.to_account_info(),
+                    authority: ctx.accounts.holder.to_account_info(),
+                },
+            );
+            token::burn(cpi_ctx, 1)?;
+        }
+
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        pool.user_stake_count = pool.user_stake_count.checked_sub(1).unwrap();
+        Ok(())
+    }
+
+    pub fn stake_nft(ctx: Context<StakeNft>, nonce: u8, vault_nonce: u8) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        if pool.is_paused() {
+            return Err(ErrorCode::PoolPaused.into());
+        }
+
+        let nft_metadata = metadata::Metadata::from_account_info(&ctx.accounts.nft_metadata)?;
+        if !nft_metadata.is_verified_member(&ctx.accounts.nft_mint.key(), &pool.nft_collection) {
+            return Err(ErrorCode::NftNotInCollection.into());
+        }
+
+        let user = &mut ctx.accounts.user.load_mut()?;
+        let nft_reward_rate = pool.nft_reward_rate;
+        update_nft_rewards(user, nft_reward_rate).unwrap();
+        user.balance_staked = user.balance_staked.checked_add(1).unwrap();
+        pool.total_staked = pool.total_staked.checked_add(1).unwrap();
+        update_nft_rewards(user, nft_reward_rate).unwrap();
+
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        let staked_nft = &mut ctx.accounts.staked_nft;
+        staked_nft.pool = ctx.accounts.pool.key();
+        staked_nft.owner = ctx.accounts.owner.key();
+        staked_nft.mint = ctx.accounts.nft_mint.key();
+        staked_nft.stake_time = current_time;
+        staked_nft.nonce = nonce;
+        staked_nft.vault_nonce = vault_nonce;
+
+        // Escrow the nft in its vault.
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.nft_from_account.to_account_info(),
+                    to: ctx.accounts.nft_vault.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            );
+            token::transfer(cpi_ctx, 1)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn unstake_nft(ctx: Context<UnstakeNft>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        let user = &mut ctx.accounts.user.load_mut()?;
+        let nft_reward_rate = pool.nft_reward_rate;
+        update_nft_rewards(user, nft_reward_rate).unwrap();
+        user.balance_staked = user.balance_staked.checked_sub(1).unwrap();
+        pool.total_staked = pool.total_staked.checked_sub(1).unwrap();
+        update_nft_rewards(user, nft_reward_rate).unwrap();
+
+        let seeds = &[
+            ctx.accounts.pool.to_account_info().key.as_ref(),
+            &[pool.nonce],
+        ];
+        let pool_signer = &[&seeds[..]];
+
+        // Return the nft and close its vault.
+        {
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    to: ctx.accounts.nft_to_account.to_account_info(),
+                    authority: ctx.accounts.pool_signer.to_account_info(),
+                },
+                pool_signer,
+            );
+            token::transfer(cpi_ctx, 1)?;
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::CloseAccount {
+                    account: ctx.accounts.nft_vault.to_account_info(),
+                    destination: ctx.accounts.owner.to_account_info(),
+                    authority: ctx.accounts.pool_signer.to_account_info(),
+                },
+                pool_signer,
+            );
+            token::close_account(cpi_ctx)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn claim_nft_rewards(ctx: Context<ClaimNftRewards>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        let user = &mut ctx.accounts.user.load_mut()?;
+        let nft_reward_rate = pool.nft_reward_rate;
+        update_nft_rewards(user, nft_reward_rate).unwrap();
+
+        let seeds = &[
+            ctx.accounts.pool.to_account_info().key.as_ref(),
+            &[pool.nonce],
+        ];
+        let pool_signer = &[&seeds[..]];
+
+        //whatever the vault cannot cover stays owed to the user
+        if user.reward_a > 0 {
+            let reward_amount = user.reward_a.min(pool.reward_a_available(ctx.accounts.reward_a_vault.amount));
+            user.reward_a = user.reward_a.checked_sub(reward_amount).unwrap();
+
+            if reward_amount > 0 {
+                let cpi_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: ctx.accounts.reward_a_vault.to_account_info(),
+                        to: ctx.accounts.reward_a_account.to_account_info(),
+                        authority: ctx.accounts.pool_signer.to_account_info(),
+                    },
+                    pool_signer,
+                );
+                token::transfer(cpi_ctx, reward_amount)?;
+                pool.total_rewards_a_paid = pool.total_rewards_a_paid.checked_add(reward_amount).unwrap();
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn create_lock(
+        ctx: Context<CreateLock>,
+        nonce: u8,
+        vault_nonce: u8,
+        amount: u64,
+        lock_end: u64,
+    ) -> Result<()> {
+        if amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        if lock_end <= current_time || lock_end > current_time.checked_add(MAX_LOCK_DURATION).unwrap() {
+            return Err(ErrorCode::InvalidLockEnd.into());
+        }
+
+        let vote_escrow = &mut ctx.accounts.vote_escrow;
+        vote_escrow.pool = ctx.accounts.pool.key();
+        vote_escrow.owner = ctx.accounts.owner.key();
+        vote_escrow.amount = amount;
+        vote_escrow.lock_start = current_time;
+        vote_escrow.lock_end = lock_end;
+        vote_escrow.nonce = nonce;
+        vote_escrow.vault_nonce = vault_nonce;
+        pool.total_locked = pool.total_locked.checked_add(amount).unwrap();
+
+        // Transfer tokens into the escrow vault.
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.lock_from_account.to_account_info(),
+                    to: ctx.accounts.escrow_vault.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            );
+            token::transfer(cpi_ctx, amount)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn increase_lock_amount(ctx: Context<IncreaseLockAmount>, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Err(ErrorCode::AmountMustBeGreaterThanZero.into());
+        }
+
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        let vote_escrow = &mut ctx.accounts.vote_escrow;
+        if vote_escrow.lock_end <= current_time {
+            return Err(ErrorCode::LockExpired.into());
+        }
+
+        vote_escrow.amount = vote_escrow.amount.checked_add(amount).unwrap();
+        pool.total_locked = pool.total_locked.checked_add(amount).unwrap();
+
+        // Transfer tokens into the escrow vault.
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.lock_from_account.to_account_info(),
+                    to: ctx.accounts.escrow_vault.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            );
+            token::transfer(cpi_ctx, amount)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn extend_lock(ctx: Context<ExtendLock>, lock_end: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        let vote_escrow = &mut ctx.accounts.vote_escrow;
+        if vote_escrow.lock_end <= current_time {
+            return Err(ErrorCode::LockExpired.into());
+        }
+        if lock_end <= vote_escrow.lock_end {
+            return Err(ErrorCode::LockCannotBeShortened.into());
+        }
+        if lock_end > current_time.checked_add(MAX_LOCK_DURATION).unwrap() {
+            return Err(ErrorCode::InvalidLockEnd.into());
+        }
+
+        vote_escrow.lock_end = lock_end;
+
+        Ok(())
+    }
+
+    pub fn withdraw_lock(ctx: Context<WithdrawLock>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        let amount = ctx.accounts.vote_escrow.amount;
+        if ctx.accounts.vote_escrow.lock_end > current_time {
+            return Err(ErrorCode::LockNotExpired.into());
+        }
+        pool.total_locked = pool.total_locked.checked_sub(amount).unwrap();
+
+        let seeds = &[
+            ctx.accounts.pool.to_account_info().key.as_ref(),
+            &[pool.nonce],
+        ];
+        let pool_signer = &[&seeds[..]];
+
+        // Return the tokens and close the escrow vault.
+        {
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.escrow_vault.to_account_info(),
+                    to: ctx.accounts.lock_to_account.to_account_info(),
+                    authority: ctx.accounts.pool_signer.to_account_info(),
+                },
+                pool_signer,
+            );
+            token::transfer(cpi_ctx, amount)?;
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::CloseAccount {
+                    account: ctx.accounts.escrow_vault.to_account_info(),
+                    destination: ctx.accounts.owner.to_account_info(),
+                    authority: ctx.accounts.pool_signer.to_account_info(),
+                },
+                pool_signer,
+            );
+            token::close_account(cpi_ctx)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_governance_realm(ctx: Context<SetPoolConfig>, realm: Pubkey) -> Result<()> {
+        let pool = &mut ctx.accounts.pool.load_mut()?;
+        pool.record_activity();
+        pool.governance_realm = realm;
+
+        Ok(())
+    }
+
+    pub fn update_voter_weight_record(ctx: Context<UpdateVoterWeightRecord>) -> Result<()> {
+        let pool = ctx.accounts.pool.load()?;
+        let user = ctx.accounts.user.load()?;
+
+        //governance only accepts weights refreshed in the same slot
+        let record = &mut ctx.accounts.voter_weight_record;
+        record.realm = pool.governance_realm;
+        record.governing_token_mint = pool.staking_mint;
+        record.governing_token_owner = user.owner;
+        record.voter_weight = user.current_balance_staked(&pool);
+        record.voter_weight_expiry = Some(clock::Clock::get()?.slot);
+        record.weight_action = None;
+        record.weight_action_target = None;
+
+        Ok(())
+    }
+
+    pub fn update_max_voter_weight_record(ctx: Context<UpdateMaxVoterWeightRecord>) -> Result<()> {
+        let pool = ctx.accounts.pool.load()?;
+
+        let record = &mut ctx.accounts.max_voter_weight_record;
+        record.realm = pool.governance_realm;
+        record.governing_token_mint = pool.staking_mint;
+        record.max_voter_weight = pool.total_staked;
+        record.max_voter_weight_expiry = Some(clock::Clock::get()?.slot);
+
+        Ok(())
+    }
+
+    pub fn create_gauge_controller(
+        ctx: Context<CreateGaugeController>,
+        nonce: u8,
+        emission_per_epoch: u64,
+        epoch_duration: u64,
+    ) -> Result<()> {
+        if epoch_duration == 0 {
+            return Err(ErrorCode::InvalidEpochDuration.into());
+        }
+
+        let controller = &mut ctx.accounts.controller;
+        controller.authority = ctx.accounts.authority.key();
+        controller.escrow_pool = ctx.accounts.escrow_pool.key();
+        controller.reward_a_mint = ctx.accounts.reward_a_mint.key();
+        controller.emission_vault = ctx.accounts.emission_vault.key();
+        controller.nonce = nonce;
+        controller.emission_per_epoch = emission_per_epoch;
+        controller.epoch_duration = epoch_duration;
+        controller.genesis_time = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+
+        Ok(())
+    }
+
+    pub fn fund_gauge_controller(ctx: Context<FundGaugeController>, amount: u64) -> Result<()> {
+        //emissions are paid out of this vault at each checkpoint
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.from.to_account_info(),
+                to: ctx.accounts.emission_vault.to_account_info(),
+                authority: ctx.accounts.funder.to_account_info(),
+            },
+        );
+        token::transfer(cpi_ctx, amount)?;
+
+        let controller = &mut ctx.accounts.controller;
+        controller.total_funded = controller.total_funded.checked_add(amount).unwrap();
+
+        Ok(())
+    }
+
+    pub fn set_gauge_emission(ctx: Context<SetGaugeEmission>, emission_per_epoch: u64) -> Result<()> {
+        //takes effect from the next checkpoint
+        ctx.accounts.controller.emission_per_epoch = emission_per_epoch;
+
+        Ok(())
+    }
+
+    pub fn create_gauge(ctx: Context<CreateGauge>, nonce: u8) -> Result<()> {
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        let gauge = &mut ctx.accounts.gauge;
+        gauge.controller = ctx.accounts.controller.key();
+        gauge.pool = ctx.accounts.pool.key();
+        gauge.epoch = gauge_epoch(&ctx.accounts.controller, current_time);
+        gauge.nonce = nonce;
+
+        Ok(())
+    }
+
+    pub fn vote_gauge(ctx: Context<VoteGauge>, voter_nonce: u8, vote_nonce: u8, bps: u16) -> Result<()> {
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        let controller = &mut ctx.accounts.controller;
+        let epoch = gauge_epoch(controller, current_time);
+        roll_gauge_controller(controller, epoch);
+        let gauge = &mut ctx.accounts.gauge;
+        roll_gauge(gauge, epoch);
+
+        let voter = &mut ctx.accounts.voter;
+        voter.controller = controller.key();
+        voter.owner = ctx.accounts.owner.key();
+        voter.nonce = voter_nonce;
+        if voter.epoch != epoch {
+            voter.used_bps = 0;
+            voter.epoch = epoch;
+        }
+
+        //a repeat vote in the same epoch replaces the earlier one
+        let gauge_vote = &mut ctx.accounts.gauge_vote;
+        gauge_vote.gauge = gauge.key();
+        gauge_vote.owner = ctx.accounts.owner.key();
+        gauge_vote.nonce = vote_nonce;
+        if gauge_vote.epoch == epoch {
+            gauge.weight = gauge.weight.checked_sub(gauge_vote.power).unwrap();
+            controller.total_weight = controller.total_weight.checked_sub(gauge_vote.power).unwrap();
+            voter.used_bps = voter.used_bps.checked_sub(gauge_vote.bps).unwrap();
+        }
+
+        voter.used_bps = voter.used_bps.checked_add(bps).unwrap();
+        if voter.used_bps > MAX_GAUGE_VOTE_BPS {
+            return Err(ErrorCode::GaugeVoteOverAllocated.into());
+        }
+
+        let vote_escrow = &ctx.accounts.vote_escrow;
+        let power: u64 = (voting_power(vote_escrow.amount, vote_escrow.lock_end, current_time) as u128)
+            .checked_mul(bps as u128)
+            .unwrap()
+            .checked_div(MAX_GAUGE_VOTE_BPS as u128)
+            .unwrap()
+            .try_into()
+            .unwrap();
+        gauge_vote.epoch = epoch;
+        gauge_vote.bps = bps;
+        gauge_vote.power = power;
+        gauge.weight = gauge.weight.checked_add(power).unwrap();
+        controller.total_weight = controller.total_weight.checked_add(power).unwrap();
+
+        Ok(())
+    }
+
+    pub fn checkpoint_gauges<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, CheckpointGauges<'info>>) -> Result<()> {
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        let controller = &mut ctx.accounts.controller;
+        let epoch = gauge_epoch(controller, current_time);
+        roll_gauge_controller(controller, epoch);
+        let epoch_end = controller
+            .genesis_time
+            .checked_add(epoch.checked_add(1).unwrap().checked_mul(controller.epoch_duration).unwrap())
+            .unwrap();
+
+        let controller_key = controller.key();
+        let seeds = &[controller_key.as_ref(), &[controller.nonce]];
+        let controller_signer = &[&seeds[..]];
+
+        //remaining accounts are (gauge, pool, reward a vault) triples, votes from the last epoch split this one
+        let mut available = ctx.accounts.emission_vault.amount;
+        let mut accounts = ctx.remaining_accounts.iter();
+        while let Some(gauge_info) = accounts.next() {
+            let pool_info = accounts.next().ok_or(ErrorCode::MissingGaugeAccounts)?;
+            let vault_info = accounts.next().ok_or(ErrorCode::MissingGaugeAccounts)?;
+            let mut gauge: Account<Gauge> = Account::try_from(gauge_info)?;
+            if gauge.controller != controller_key || gauge.pool != pool_info.key() {
+                return Err(ErrorCode::InvalidGauge.into());
+            }
+            roll_gauge(&mut gauge, epoch);
+            if gauge.checkpoint_epoch == epoch && gauge.checkpointed {
+                continue;
+            }
+
+            let share: u64 = if controller.last_total_weight == 0 {
+                0
+            } else {
+                (controller.emission_per_epoch as u128)
+                    .checked_mul(gauge.last_weight as u128)
+                    .unwrap()
+                    .checked_div(controller.last_total_weight as u128)
+                    .unwrap()
+                    .try_into()
+                    .unwrap()
+            };
+            //an underfunded controller pays out what it holds
+            let amount = share.min(available);
+            available = available.checked_sub(amount).unwrap();
+
+            let loader: AccountLoader<Pool> = AccountLoader::try_from(pool_info)?;
+            let pool = &mut loader.load_mut()?;
+            if pool.reward_a_vault != vault_info.key() {
+                return Err(ErrorCode::InvalidRewardVault.into());
+            }
+            pool.record_activity();
+            update_rewards(pool, None).unwrap();
+
+            //whatever is left of a running campaign rolls into the epoch, like notify_reward_amount
+            let mut total_amount = amount;
+            if current_time < pool.period_finish {
+                let leftover = pool.period_finish
+                    .checked_sub(current_time)
+                    .unwrap()
+                    .checked_mul(pool.reward_a_rate)
+                    .unwrap();
+                total_amount = total_amount.checked_add(leftover).unwrap();
+            }
+            let period_finish = pool.period_finish.max(epoch_end);
+            pool.reward_a_rate = total_amount.checked_div(period_finish.checked_sub(current_time).unwrap()).unwrap();
+            pool.last_update_time = current_time;
+            pool.period_finish = period_finish;
+
+            if amount > 0 {
+                let cpi_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: ctx.accounts.emission_vault.to_account_info(),
+                        to: vault_info.clone(),
+                        authority: ctx.accounts.controller_signer.to_account_info(),
+                    },
+                    controller_signer,
+                );
+                token::transfer(cpi_ctx, amount)?;
+                pool.total_funded_a = pool.total_funded_a.checked_add(amount).unwrap();
+            }
+
+            gauge.checkpoint_epoch = epoch;
+            gauge.checkpointed = true;
+            gauge.exit(ctx.program_id)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_pending_rewards(ctx: Context<UserView>) -> Result<()> {
+        //accrue on copies so nothing is written back
+        let mut pool = *ctx.accounts.pool.load()?;
+        let mut user = *ctx.accounts.user.load()?;
+        if pool.kind() == PoolKind::Nft {
+            update_nft_rewards(&mut user, pool.nft_reward_rate)?;
+        } else {
+            update_rewards(&mut pool, Some(&mut user))?;
+        }
+
+        set_view_return(&PendingRewards {
+            reward_a: user.reward_a,
+            reward_b: user.reward_b,
+            extra_rewards: user.extra_rewards,
+        });
+
+        Ok(())
+    }
+
+    pub fn get_pool_summary(ctx: Context<PoolView>) -> Result<()> {
+        //accrue on a copy so nothing is written back
+        let mut pool = *ctx.accounts.pool.load()?;
+        if pool.kind() == PoolKind::Fungible {
+            update_rewards(&mut pool, None)?;
+        }
+
+        set_view_return(&PoolSummary {
+            paused: pool.is_paused(),
+            user_stake_count: pool.user_stake_count,
+            total_staked: pool.total_staked,
+            reward_a_rate: pool.reward_a_rate,
+            reward_a_per_token_stored: pool.reward_a_per_token_stored,
+            period_finish: pool.period_finish,
+            nft_reward_rate: pool.nft_reward_rate,
+            total_rewards_a_paid: pool.total_rewards_a_paid,
+            total_rewards_b_paid: pool.total_rewards_b_paid,
+            total_funded_a: pool.total_funded_a,
+            total_funded_b: pool.total_funded_b,
+            last_activity_time: pool.last_activity_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn get_unlock_time(ctx: Context<UserView>) -> Result<()> {
+        let pool = ctx.accounts.pool.load()?;
+        let user = ctx.accounts.user.load()?;
+        let unlock_time = unlock_time(&pool, user.stake_time);
+        let current_time: u64 = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+
+        set_view_return(&UnlockTime {
+            stake_time: user.stake_time,
+            unlock_time,
+            unlocked: current_time >= unlock_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn get_remaining_capacity(ctx: Context<UserView>) -> Result<()> {
+        let pool = ctx.accounts.pool.load()?;
+        let user = ctx.accounts.user.load()?;
+
+        set_view_return(&RemainingCapacity {
+            max_total_staked: pool.max_total_staked,
+            max_per_user: pool.max_per_user,
+            remaining: pool.remaining_capacity(&user),
+        });
+
+        Ok(())
+    }
+
+    pub fn get_voting_power(ctx: Context<VoteEscrowView>, timestamp: u64) -> Result<()> {
+        let vote_escrow = &ctx.accounts.vote_escrow;
+
+        set_view_return(&VotingPower {
+            amount: vote_escrow.amount,
+            lock_end: vote_escrow.lock_end,
+            voting_power: voting_power(vote_escrow.amount, vote_escrow.lock_end, timestamp),
+        });
+
+        Ok(())
+    }
+
+    pub fn close_pool<'info>(ctx: Context<ClosePool>) -> Result<()> {
+        let pool = &ctx.accounts.pool;
+        
+        let signer_seeds = &[pool.to_account_info().key.as_ref(), &[pool.load()?.nonce]];
+        
+        //instead of closing these vaults, we could technically just 
+        //set_authority on them. it's not very ata clean, but it'd work
+        //if size of tx is an issue, thats an approach
+
+        //close staking vault
+        close_pool_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.staking_vault,
+            &ctx.accounts.staking_refundee,
+            &ctx.accounts.refundee,
+            &ctx.accounts.pool_signer,
+            signer_seeds,
+        )?;
+        
+        //close token a vault
+        close_pool_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.reward_a_vault,
+            &ctx.accounts.reward_a_refundee,
+            &ctx.accounts.refundee,
+            &ctx.accounts.pool_signer,
+            signer_seeds,
+        )?;
+        
+        Ok(())
+    }
+
+    pub fn close_nft_pool(ctx: Context<CloseNftPool>) -> Result<()> {
+        let pool = &ctx.accounts.pool;
+        let signer_seeds = &[pool.to_account_info().key.as_ref(), &[pool.load()?.nonce]];
+
+        //nft pools escrow each nft in its own vault, so only reward a is left
+        close_pool_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.reward_a_vault,
+            &ctx.accounts.reward_a_refundee,
+            &ctx.accounts.refundee,
+            &ctx.accounts.pool_signer,
+            signer_seeds,
+        )?;
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(pool_nonce: u8)]
+pub struct Initialize<'info> {
+    authority: UncheckedAccount<'info>,
+
+    staking_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [
+            b"staking_vault".as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump,
+        token::mint = staking_mint,
+        token::authority = pool_signer,
+    )]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+
+    reward_a_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [
+            b"reward_a_vault".as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump,
+        token::mint = reward_a_mint,
+        token::authority = pool_signer,
+    )]
+    reward_a_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool_nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    #[account(
+        zero,
+    )]
+    pool: AccountLoader<'info, Pool>,
+
+    #[account(mut)]
+    payer: Signer<'info>,
+    
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u8)]
+pub struct InitializeRegistry<'info> {
+    #[account(
+        init,
+        payer = upgrade_authority,
+        seeds = [
+            b"registry".as_ref()
+        ],
+        bump = nonce,
+        space = PoolRegistry::LEN,
+    )]
+    registry: Box<Account<'info, PoolRegistry>>,
+    authority: UncheckedAccount<'info>,
+    // Only the program's upgrade authority may pick the registry authority.
+    #[account(
+        mut,
+        constraint = program_upgrade_authority(&program_data) == Some(upgrade_authority.key()) @ ErrorCode::NotUpgradeAuthority,
+    )]
+    upgrade_authority: Signer<'info>,
+    #[account(address = find_program_data_address().0)]
+    program_data: UncheckedAccount<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u32, pool_bump: u8, pool_nonce: u8)]
+pub struct CreatePool<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [
+            b"registry".as_ref()
+        ],
+        bump = registry.nonce,
+    )]
+    registry: Box<Account<'info, PoolRegistry>>,
+    #[account(mut)]
+    authority: Signer<'info>,
+
+    staking_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [
+            b"staking_vault".as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump,
+        token::mint = staking_mint,
+        token::authority = pool_signer,
+    )]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+
+    reward_a_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [
+            b"reward_a_vault".as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump,
+        token::mint = reward_a_mint,
+        token::authority = pool_signer,
+    )]
+    reward_a_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool_nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [
+            b"pool".as_ref(),
+            staking_mint.key().as_ref(),
+            reward_a_mint.key().as_ref(),
+            index.to_le_bytes().as_ref()
+        ],
+        bump = pool_bump,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(pool_nonce: u8)]
+pub struct InitializeNftPool<'info> {
+    authority: UncheckedAccount<'info>,
+
+    reward_a_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [
+            b"reward_a_vault".as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump,
+        token::mint = reward_a_mint,
+        token::authority = pool_signer,
+    )]
+    reward_a_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool_nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    #[account(
+        zero,
+    )]
+    pool: AccountLoader<'info, Pool>,
+
+    #[account(mut)]
+    payer: Signer<'info>,
+    
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u8)]
+pub struct CreateUser<'info> {
+    // Stake instance.
+    #[account(
+        mut,
+        constraint = !pool.load()?.is_paused(),
+    )]
+    pool: AccountLoader<'info, Pool>,
+    // Member.
+    #[account(
+        init,
+        payer = payer,
+        seeds = [
+            owner.key.as_ref(), 
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = nonce,
+    )]
+    user: AccountLoader<'info, User>,
+    owner: Signer<'info>,
+    // Pays the user rent, so program owners need no lamports of their own.
+    #[account(mut)]
+    payer: Signer<'info>,
+    // Misc.
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Pause<'info> {
+    #[account(
+        mut, 
+        has_one = authority,
+        constraint = !pool.load()?.is_paused(),
+    )]
+    pool: AccountLoader<'info, Pool>,
+    authority: Signer<'info>,
+
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct Unpause<'info> {
+    #[account(
+        mut, 
+        has_one = authority,
+        constraint = pool.load()?.is_paused(),
+    )]
+    pool: AccountLoader<'info, Pool>,
+    authority: Signer<'info>,
+
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct Stake<'info> {
+    // Global accounts for the staking instance.
+    #[account(
+        mut, 
+        has_one = staking_vault,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(
+        mut,
+        constraint = staking_vault.owner == *pool_signer.key,
+    )]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+
+    // User.
+    #[account(
+        mut, 
+        has_one = owner, 
+        has_one = pool,
+        seeds = [
+            owner.key.as_ref(), 
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = user.load()?.nonce,
+    )]
+    user: AccountLoader<'info, User>,
+    owner: Signer<'info>,
+    #[account(
+        mut,
+        constraint = stake_from_account.mint == pool.load()?.staking_mint @ ErrorCode::StakeFromAccountMintMismatch,
+        constraint = stake_from_account.owner == owner.key() @ ErrorCode::StakeFromAccountOwnerMismatch,
+    )]
+    stake_from_account: Box<Account<'info, TokenAccount>>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u8)]
+pub struct StakeFor<'info> {
+    // Global accounts for the staking instance.
+    #[account(
+        mut, 
+        has_one = staking_vault,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(
+        mut,
+        constraint = staking_vault.owner == *pool_signer.key,
+    )]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+
+    // Beneficiary user, created by the payer on the first deposit.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [
+            beneficiary.key.as_ref(), 
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = nonce,
+    )]
+    user: AccountLoader<'info, User>,
+    beneficiary: UncheckedAccount<'info>,
+
+    // Funding wallet.
+    #[account(mut)]
+    payer: Signer<'info>,
+    #[account(
+        mut,
+        constraint = stake_from_account.mint == pool.load()?.staking_mint @ ErrorCode::StakeFromAccountMintMismatch,
+        constraint = stake_from_account.owner == payer.key() @ ErrorCode::StakeFromAccountOwnerMismatch,
+    )]
+    stake_from_account: Box<Account<'info, TokenAccount>>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u8)]
+pub struct TransferPosition<'info> {
+    #[account(mut)]
+    pool: AccountLoader<'info, Pool>,
+
+    // Sending user.
+    #[account(
+        mut, 
+        has_one = owner, 
+        has_one = pool,
+        seeds = [
+            owner.key.as_ref(), 
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = from_user.load()?.nonce,
+    )]
+    from_user: AccountLoader<'info, User>,
+    #[account(mut)]
+    owner: Signer<'info>,
+
+    // Receiving user, created if it does not exist yet.
+    #[account(
+        init_if_needed,
+        payer = owner,
+        seeds = [
+            recipient.key.as_ref(), 
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = nonce,
+    )]
+    to_user: AccountLoader<'info, User>,
+    #[account(
+        constraint = recipient.key() != owner.key() @ ErrorCode::CannotTransferToSelf,
+    )]
+    recipient: UncheckedAccount<'info>,
+
+    // Misc.
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPoolConfig<'info> {
+    #[account(
+        mut, 
+        has_one = authority,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferralRewards<'info> {
+    #[account(
+        mut,
+        constraint = pool.load()?.kind() == PoolKind::Fungible @ ErrorCode::WrongPoolKind,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(
+        mut,
+        has_one = pool,
+        constraint = referee.load()?.referrer == referrer.key() @ ErrorCode::InvalidReferrer,
+    )]
+    referee: AccountLoader<'info, User>,
+    #[account(
+        mut,
+        has_one = pool,
+    )]
+    referrer: AccountLoader<'info, User>,
+}
+
+#[derive(Accounts)]
+pub struct RefreshTier<'info> {
+    #[account(
+        mut,
+        constraint = pool.load()?.kind() == PoolKind::Fungible @ ErrorCode::WrongPoolKind,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(
+        mut,
+        has_one = pool,
+    )]
+    user: AccountLoader<'info, User>,
+}
+
+#[derive(Accounts)]
+pub struct PokeBoost<'info> {
+    #[account(
+        mut,
+        constraint = pool.load()?.kind() == PoolKind::Fungible @ ErrorCode::WrongPoolKind,
+        constraint = pool.load()?.boost_pool != Pubkey::default() @ ErrorCode::BoostNotEnabled,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(
+        mut,
+        has_one = pool,
+    )]
+    user: AccountLoader<'info, User>,
+    #[account(
+        address = pool.load()?.boost_pool,
+    )]
+    boost_pool: AccountLoader<'info, Pool>,
+    // The user owner's account in the boost pool, possibly not created.
+    #[account(
+        address = find_user_address(&user.load()?.owner, &boost_pool.key()).0,
+    )]
+    boost_user: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    // Global accounts for the staking instance.
+    #[account(
+        mut, 
+        has_one = staking_vault,
+        has_one = authority,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(
+        mut,
+        constraint = staking_vault.owner == *pool_signer.key,
+    )]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+
+    authority: Signer<'info>,
+    #[account(
+        mut,
+    )]
+    withdraw_account: Box<Account<'info, TokenAccount>>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct FunderChange<'info> {
+    // Global accounts for the staking instance.
+    #[account(
+        mut, 
+        has_one = authority,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Fund<'info> {
+    // Global accounts for the staking instance.
+    #[account(
+        mut, 
+        has_one = reward_a_vault,
+        constraint = !pool.load()?.is_paused(),
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(mut)]
+    reward_a_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        //require signed funder auth - otherwise constant micro fund could hold funds hostage
+        constraint = funder.key() == pool.load()?.authority || pool.load()?.funders.iter().any(|x| *x == funder.key()),
+    )]
+    funder: Signer<'info>,
+    #[account(mut)]
+    from_a: Box<Account<'info, TokenAccount>>,
+    
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct FundStaking<'info> {
+    // Global accounts for the staking instance.
+    #[account(
+        mut, 
+        has_one = staking_vault,
+        constraint = !pool.load()?.is_paused(),
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(mut)]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        //require signed funder auth - otherwise constant micro fund could hold funds hostage
+        constraint = funder.key() == pool.load()?.authority || pool.load()?.funders.iter().any(|x| *x == funder.key()),
+    )]
+    funder: Signer<'info>,
+    #[account(mut)]
+    from: Box<Account<'info, TokenAccount>>,
+    
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct AddReward<'info> {
+    #[account(
+        mut, 
+        has_one = authority,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(mut)]
+    authority: Signer<'info>,
+
+    reward_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [
+            b"reward_vault".as_ref(),
+            pool.to_account_info().key.as_ref(),
+            reward_mint.key().as_ref()
+        ],
+        bump,
+        token::mint = reward_mint,
+        token::authority = pool_signer,
+    )]
+    reward_vault: Box<Account<'info, TokenAccount>>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct FundExtraReward<'info> {
+    // Global accounts for the staking instance.
+    #[account(
+        mut, 
+        constraint = !pool.load()?.is_paused(),
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(mut)]
+    reward_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        //require signed funder auth - otherwise constant micro fund could hold funds hostage
+        constraint = funder.key() == pool.load()?.authority || pool.load()?.funders.iter().any(|x| *x == funder.key()),
+    )]
+    funder: Signer<'info>,
+    #[account(mut)]
+    from: Box<Account<'info, TokenAccount>>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReward<'info> {
+    // Global accounts for the staking instance.
+    #[account(
+        mut, 
+        has_one = staking_vault,
+        has_one = reward_a_vault,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(mut)]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    reward_a_vault: Box<Account<'info, TokenAccount>>,
+
+    // User.
+    #[account(
+        mut,
+        has_one = owner,
+        has_one = pool,
+        seeds = [
+            owner.to_account_info().key.as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = user.load()?.nonce,
+    )]
+    user: AccountLoader<'info, User>,
+    #[account(mut)]
+    owner: Signer<'info>,
+
+    // Claim destinations, created as the owner's associated token accounts if missing.
+    #[account(
+        constraint = staking_mint.key() == pool.load()?.staking_mint,
+    )]
+    staking_mint: Box<Account<'info, Mint>>,
+    #[account(
+        constraint = reward_a_mint.key() == pool.load()?.reward_a_mint,
+    )]
+    reward_a_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = reward_a_mint,
+        associated_token::authority = owner,
+        //init_if_needed skips the derivation check for existing accounts
+        constraint = reward_a_account.key() == associated_token::get_associated_token_address(&owner.key(), &pool.load()?.reward_a_mint) @ ErrorCode::RewardAAccountNotAssociated,
+        constraint = reward_a_account.mint == pool.load()?.reward_a_mint @ ErrorCode::RewardAAccountMintMismatch,
+        constraint = reward_a_account.owner == owner.key() @ ErrorCode::RewardAAccountOwnerMismatch,
+    )]
+    reward_a_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = staking_mint,
+        associated_token::authority = owner,
+        constraint = reward_b_account.key() == associated_token::get_associated_token_address(&owner.key(), &pool.load()?.staking_mint) @ ErrorCode::RewardBAccountNotAssociated,
+        constraint = reward_b_account.mint == pool.load()?.staking_mint @ ErrorCode::RewardBAccountMintMismatch,
+        constraint = reward_b_account.owner == owner.key() @ ErrorCode::RewardBAccountOwnerMismatch,
+    )]
+    reward_b_account: Box<Account<'info, TokenAccount>>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+    associated_token_program: Program<'info, AssociatedToken>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct StartCooldown<'info> {
+    #[account(mut)]
+    pool: AccountLoader<'info, Pool>,
+    #[account(
+        mut,
+        has_one = owner,
+        has_one = pool,
+        //a wallet's own user, not a position whose mint the signer once held
+        seeds = [
+            owner.to_account_info().key.as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = user.load()?.nonce,
+    )]
+    user: AccountLoader<'info, User>,
+    owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Slash<'info> {
+    #[account(
+        mut,
+        has_one = staking_vault,
+        constraint = pool.load()?.kind() == PoolKind::Fungible @ ErrorCode::WrongPoolKind,
+        constraint = pool.load()?.slasher == slasher.key() @ ErrorCode::InvalidSlasher,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(mut)]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+    slasher: Signer<'info>,
+    #[account(
+        mut,
+        constraint = destination.mint == pool.load()?.staking_mint @ ErrorCode::SlashDestinationMintMismatch,
+    )]
+    destination: Box<Account<'info, TokenAccount>>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseVested<'info> {
+    #[account(
+        mut,
+        has_one = reward_a_vault,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(mut)]
+    reward_a_vault: Box<Account<'info, TokenAccount>>,
+
+    // User.
+    #[account(
+        mut,
+        has_one = owner,
+        has_one = pool,
+        seeds = [
+            owner.to_account_info().key.as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = user.load()?.nonce,
+    )]
+    user: AccountLoader<'info, User>,
+    owner: Signer<'info>,
+    #[account(
+        mut,
+        constraint = reward_a_account.mint == pool.load()?.reward_a_mint @ ErrorCode::RewardAAccountMintMismatch,
+        constraint = reward_a_account.owner == owner.key() @ ErrorCode::RewardAAccountOwnerMismatch,
+    )]
+    reward_a_account: Box<Account<'info, TokenAccount>>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CloseUser<'info> {
+    #[account(
+        mut, 
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        has_one = pool,
+        seeds = [
+            owner.to_account_info().key.as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = user.load()?.nonce,
+        constraint = user.load()?.balance_staked == 0,
+        constraint = user.load()?.reward_a == 0,
+        constraint = user.load()?.reward_b == 0,
+        constraint = user.load()?.extra_rewards_settled(),
+        constraint = user.load()?.vesting_settled(),
+    )]
+    user: AccountLoader<'info, User>,
+    owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePool<'info> {
+    // Stake instance, possibly in an older layout.
+    #[account(
+        mut,
+        owner = ID,
+    )]
+    pool: UncheckedAccount<'info>,
+    // Pays for the extra rent.
+    #[account(mut)]
+    payer: Signer<'info>,
+    // Misc.
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateUser<'info> {
+    // Member, possibly in an older layout.
+    #[account(
+        mut,
+        owner = ID,
+    )]
+    user: UncheckedAccount<'info>,
+    // Stake instance the user belongs to, already migrated.
+    #[account(mut)]
+    pool: AccountLoader<'info, Pool>,
+    // Pays for the extra rent.
+    #[account(mut)]
+    payer: Signer<'info>,
+    // Misc.
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u8)]
+pub struct StakePosition<'info> {
+    // Global accounts for the staking instance.
+    #[account(
+        mut, 
+        has_one = staking_vault,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(
+        mut,
+        constraint = staking_vault.owner == *pool_signer.key,
+    )]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+
+    // Position nft.
+    #[account(
+        init,
+        payer = owner,
+        mint::decimals = 0,
+        mint::authority = pool_signer,
+    )]
+    position_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        payer = owner,
+        seeds = [
+            b"position".as_ref(),
+            position_mint.key().as_ref()
+        ],
+        bump = nonce,
+    )]
+    position: AccountLoader<'info, User>,
+    #[account(
+        init,
+        payer = owner,
+        associated_token::mint = position_mint,
+        associated_token::authority = owner,
+    )]
+    position_token_account: Box<Account<'info, TokenAccount>>,
+
+    // Staker.
+    #[account(mut)]
+    owner: Signer<'info>,
+    #[account(
+        mut,
+        constraint = stake_from_account.mint == pool.load()?.staking_mint @ ErrorCode::StakeFromAccountMintMismatch,
+        constraint = stake_from_account.owner == owner.key() @ ErrorCode::StakeFromAccountOwnerMismatch,
+    )]
+    stake_from_account: Box<Account<'info, TokenAccount>>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+    associated_token_program: Program<'info, AssociatedToken>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct CreatePositionMetadata<'info> {
+    pool: AccountLoader<'info, Pool>,
+    #[account(
+        has_one = pool,
+        constraint = position.load()?.owner == position_mint.key(),
+        seeds = [
+            b"position".as_ref(),
+            position_mint.key().as_ref()
+        ],
+        bump = position.load()?.nonce,
+    )]
+    position: AccountLoader<'info, User>,
+    position_mint: Box<Account<'info, Mint>>,
+    #[account(
+        constraint = position_token_account.mint == position_mint.key(),
+    )]
+    position_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        address = metadata::find_metadata_account(&position_mint.key()).0,
+    )]
+    metadata: UncheckedAccount<'info>,
+    /// Either the holder of the position nft or the pool authority.
+    #[account(
+        constraint = holder.key() == pool.load()?.authority
+            || (position_token_account.owner == holder.key() && position_token_account.amount == 1)
+            @ ErrorCode::NotPositionHolder,
+    )]
+    holder: Signer<'info>,
+    #[account(mut)]
+    payer: Signer<'info>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    #[account(address = metadata::ID)]
+    metadata_program: UncheckedAccount<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePositionMetadata<'info> {
+    pool: AccountLoader<'info, Pool>,
+    #[account(
+        has_one = pool,
+        constraint = position.load()?.owner == position_mint.key(),
+        seeds = [
+            b"position".as_ref(),
+            position_mint.key().as_ref()
+        ],
+        bump = position.load()?.nonce,
+    )]
+    position: AccountLoader<'info, User>,
+    position_mint: Box<Account<'info, Mint>>,
+    #[account(
+        constraint = position_token_account.mint == position_mint.key(),
+    )]
+    position_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        address = metadata::find_metadata_account(&position_mint.key()).0,
+    )]
+    metadata: UncheckedAccount<'info>,
+    /// Either the holder of the position nft or the pool authority.
+    #[account(
+        constraint = holder.key() == pool.load()?.authority
+            || (position_token_account.owner == holder.key() && position_token_account.amount == 1)
+            @ ErrorCode::NotPositionHolder,
+    )]
+    holder: Signer<'info>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    #[account(address = metadata::ID)]
+    metadata_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UnstakePosition<'info> {
+    // Global accounts for the staking instance.
+    #[account(
+        mut, 
+        has_one = staking_vault,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(
+        mut,
+        constraint = staking_vault.owner == *pool_signer.key,
+    )]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+
+    // Position, authorized by holding its nft.
+    #[account(
+        mut,
+        has_one = pool,
+        constraint = position.load()?.owner == position_token_account.mint,
+        seeds = [
+            b"position".as_ref(),
+            position.load()?.owner.as_ref()
+        ],
+        bump = position.load()?.nonce,
+    )]
+    position: AccountLoader<'info, User>,
+    #[account(
+        constraint = position_token_account.owner == holder.key() @ ErrorCode::NotPositionHolder,
+        constraint = position_token_account.amount == 1 @ ErrorCode::NotPositionHolder,
+    )]
+    position_token_account: Box<Account<'info, TokenAccount>>,
+    holder: Signer<'info>,
+    #[account(
+        mut,
+        constraint = stake_to_account.mint == pool.load()?.staking_mint @ ErrorCode::StakeFromAccountMintMismatch,
+        constraint = stake_to_account.owner == holder.key() @ ErrorCode::StakeFromAccountOwnerMismatch,
+    )]
+    stake_to_account: Box<Account<'info, TokenAccount>>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct StartPositionCooldown<'info> {
+    #[account(mut)]
+    pool: AccountLoader<'info, Pool>,
+
+    // Position, authorized by holding its nft.
+    #[account(
+        mut,
+        has_one = pool,
+        constraint = position.load()?.owner == position_token_account.mint,
+        seeds = [
+            b"position".as_ref(),
+            position.load()?.owner.as_ref()
+        ],
+        bump = position.load()?.nonce,
+    )]
+    position: AccountLoader<'info, User>,
+    #[account(
+        constraint = position_token_account.owner == holder.key() @ ErrorCode::NotPositionHolder,
+        constraint = position_token_account.amount == 1 @ ErrorCode::NotPositionHolder,
+    )]
+    position_token_account: Box<Account<'info, TokenAccount>>,
+    holder: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPosition<'info> {
+    // Global accounts for the staking instance.
+    #[account(
+        mut, 
+        has_one = staking_vault,
+        has_one = reward_a_vault,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(mut)]
+    staking_vault: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    reward_a_vault: Box<Account<'info, TokenAccount>>,
+
+    // Position, authorized by holding its nft.
+    #[account(
+        mut,
+        has_one = pool,
+        constraint = position.load()?.owner == position_token_account.mint,
+        seeds = [
+            b"position".as_ref(),
+            position.load()?.owner.as_ref()
+        ],
+        bump = position.load()?.nonce,
+    )]
+    position: AccountLoader<'info, User>,
+    #[account(
+        constraint = position_token_account.owner == holder.key() @ ErrorCode::NotPositionHolder,
+        constraint = position_token_account.amount == 1 @ ErrorCode::NotPositionHolder,
+    )]
+    position_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    holder: Signer<'info>,
+
+    // Claim destinations, created as the holder's associated token accounts if missing.
+    #[account(
+        constraint = staking_mint.key() == pool.load()?.staking_mint,
+    )]
+    staking_mint: Box<Account<'info, Mint>>,
+    #[account(
+        constraint = reward_a_mint.key() == pool.load()?.reward_a_mint,
+    )]
+    reward_a_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        payer = holder,
+        associated_token::mint = reward_a_mint,
+        associated_token::authority = holder,
+        constraint = reward_a_account.key() == associated_token::get_associated_token_address(&holder.key(), &pool.load()?.reward_a_mint) @ ErrorCode::RewardAAccountNotAssociated,
+        constraint = reward_a_account.mint == pool.load()?.reward_a_mint @ ErrorCode::RewardAAccountMintMismatch,
+        constraint = reward_a_account.owner == holder.key() @ ErrorCode::RewardAAccountOwnerMismatch,
+    )]
+    reward_a_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = holder,
+        associated_token::mint = staking_mint,
+        associated_token::authority = holder,
+        constraint = reward_b_account.key() == associated_token::get_associated_token_address(&holder.key(), &pool.load()?.staking_mint) @ ErrorCode::RewardBAccountNotAssociated,
+        constraint = reward_b_account.mint == pool.load()?.staking_mint @ ErrorCode::RewardBAccountMintMismatch,
+        constraint = reward_b_account.owner == holder.key() @ ErrorCode::RewardBAccountOwnerMismatch,
+    )]
+    reward_b_account: Box<Account<'info, TokenAccount>>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+    associated_token_program: Program<'info, AssociatedToken>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    #[account(
+        mut, 
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(
+        mut,
+        close = holder,
+        has_one = pool,
+        constraint = position.load()?.owner == position_mint.key(),
+        seeds = [
+            b"position".as_ref(),
+            position_mint.key().as_ref()
+        ],
+        bump = position.load()?.nonce,
+        constraint = position.load()?.balance_staked == 0,
+        constraint = position.load()?.reward_a == 0,
+        constraint = position.load()?.reward_b == 0,
+        constraint = position.load()?.extra_rewards_settled(),
+    )]
+    position: AccountLoader<'info, User>,
+    #[account(mut)]
+    position_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = position_token_account.mint == position_mint.key(),
+        constraint = position_token_account.owner == holder.key() @ ErrorCode::NotPositionHolder,
+        constraint = position_token_account.amount == 1 @ ErrorCode::NotPositionHolder,
+    )]
+    position_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    holder: Signer<'info>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u8, vault_nonce: u8)]
+pub struct StakeNft<'info> {
+    #[account(
+        mut,
+        constraint = pool.load()?.kind() == PoolKind::Nft @ ErrorCode::WrongPoolKind,
+    )]
+    pool: AccountLoader<'info, Pool>,
+
+    // User.
+    #[account(
+        mut, 
+        has_one = owner, 
+        has_one = pool,
+        seeds = [
+            owner.key.as_ref(), 
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = user.load()?.nonce,
+    )]
+    user: AccountLoader<'info, User>,
+    #[account(mut)]
+    owner: Signer<'info>,
+
+    // Nft being staked.
+    #[account(
+        constraint = nft_mint.decimals == 0 && nft_mint.supply == 1 @ ErrorCode::NftNotInCollection,
+    )]
+    nft_mint: Box<Account<'info, Mint>>,
+    #[account(
+        address = metadata::find_metadata_account(&nft_mint.key()).0,
+    )]
+    nft_metadata: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = nft_from_account.mint == nft_mint.key(),
+        constraint = nft_from_account.owner == owner.key(),
+    )]
+    nft_from_account: Box<Account<'info, TokenAccount>>,
+
+    // Escrow.
+    #[account(
+        init,
+        payer = owner,
+        seeds = [
+            b"nft_vault".as_ref(),
+            nft_mint.key().as_ref()
+        ],
+        bump = vault_nonce,
+        token::mint = nft_mint,
+        token::authority = pool_signer,
+    )]
+    nft_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = owner,
+        seeds = [
+            b"staked_nft".as_ref(),
+            nft_mint.key().as_ref()
+        ],
+        bump = nonce,
+    )]
+    staked_nft: Box<Account<'info, StakedNft>>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct UnstakeNft<'info> {
+    #[account(
+        mut,
+        constraint = pool.load()?.kind() == PoolKind::Nft @ ErrorCode::WrongPoolKind,
+    )]
+    pool: AccountLoader<'info, Pool>,
+
+    // User.
+    #[account(
+        mut, 
+        has_one = owner, 
+        has_one = pool,
+        seeds = [
+            owner.key.as_ref(), 
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = user.load()?.nonce,
+    )]
+    user: AccountLoader<'info, User>,
+    #[account(mut)]
+    owner: Signer<'info>,
+
+    // Escrow.
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        has_one = pool,
+        seeds = [
+            b"staked_nft".as_ref(),
+            staked_nft.mint.as_ref()
+        ],
+        bump = staked_nft.nonce,
+    )]
+    staked_nft: Box<Account<'info, StakedNft>>,
+    #[account(
+        mut,
+        seeds = [
+            b"nft_vault".as_ref(),
+            staked_nft.mint.as_ref()
+        ],
+        bump = staked_nft.vault_nonce,
+    )]
+    nft_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = nft_to_account.mint == staked_nft.mint,
+        constraint = nft_to_account.owner == owner.key(),
+    )]
+    nft_to_account: Box<Account<'info, TokenAccount>>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimNftRewards<'info> {
+    // Global accounts for the staking instance.
+    #[account(
+        mut,
+        has_one = reward_a_vault,
+        constraint = pool.load()?.kind() == PoolKind::Nft @ ErrorCode::WrongPoolKind,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(mut)]
+    reward_a_vault: Box<Account<'info, TokenAccount>>,
+
+    // User.
+    #[account(
+        mut,
+        has_one = owner,
+        has_one = pool,
+        seeds = [
+            owner.to_account_info().key.as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = user.load()?.nonce,
+    )]
+    user: AccountLoader<'info, User>,
+    #[account(mut)]
+    owner: Signer<'info>,
+
+    // Claim destination, created as the owner's associated token account if missing.
+    #[account(
+        constraint = reward_a_mint.key() == pool.load()?.reward_a_mint,
+    )]
+    reward_a_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = reward_a_mint,
+        associated_token::authority = owner,
+        constraint = reward_a_account.key() == associated_token::get_associated_token_address(&owner.key(), &pool.load()?.reward_a_mint) @ ErrorCode::RewardAAccountNotAssociated,
+        constraint = reward_a_account.mint == pool.load()?.reward_a_mint @ ErrorCode::RewardAAccountMintMismatch,
+        constraint = reward_a_account.owner == owner.key() @ ErrorCode::RewardAAccountOwnerMismatch,
+    )]
+    reward_a_account: Box<Account<'info, TokenAccount>>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+    associated_token_program: Program<'info, AssociatedToken>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-#[instruction(pool_nonce: u8)]
-pub struct Initialize<'info> {
-    authority: UncheckedAccount<'info>,
-
-    staking_mint: Box<Account<'info, Mint>>,
+pub struct ClosePool<'info> {
+    #[account(mut)]
+    refundee: UncheckedAccount<'info>,
     #[account(
-        constraint = staking_vault.mint == staking_mint.key(),
+        mut,
+        constraint = staking_refundee.mint == pool.load()?.staking_mint @ ErrorCode::StakingRefundeeMintMismatch,
+        //leftover reward b funding and stray deposits are swept to the authority
+        constraint = staking_refundee.owner == authority.key() @ ErrorCode::StakingRefundeeOwnerMismatch,
+    )]
+    staking_refundee: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = reward_a_refundee.mint == pool.load()?.reward_a_mint @ ErrorCode::RewardARefundeeMintMismatch,
+        constraint = reward_a_refundee.owner == authority.key() @ ErrorCode::RewardARefundeeOwnerMismatch,
+    )]
+    reward_a_refundee: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        close = refundee,
+        has_one = authority,
+        has_one = staking_vault,
+        has_one = reward_a_vault,
+        constraint = pool.load()?.is_paused(),
+        constraint = pool.load()?.user_stake_count == 0,
+        //no stake is left to return, whatever else the staking vault holds is swept
+        constraint = pool.load()?.total_staked == 0,
+        //locks keep their tokens in escrow vaults the pool signer owns
+        constraint = pool.load()?.total_locked == 0,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    authority: Signer<'info>,
+    //vaults are pinned by has_one, pools from before the pda vaults still hold keypair vaults
+    #[account(mut,
         constraint = staking_vault.owner == pool_signer.key(),
-        //strangely, spl maintains this on owner reassignment for non-native accounts
-        //we don't want to be given an account that someone else could close when empty
-        //because in our "pool close" operation we want to assert it is still open
-        constraint = staking_vault.close_authority == COption::None,
     )]
     staking_vault: Box<Account<'info, TokenAccount>>,
-
-    reward_a_mint: Box<Account<'info, Mint>>,
-    #[account(
-        constraint = reward_a_vault.mint == reward_a_mint.key(),
+    #[account(mut,
         constraint = reward_a_vault.owner == pool_signer.key(),
-        constraint = reward_a_vault.close_authority == COption::None,
     )]
     reward_a_vault: Box<Account<'info, TokenAccount>>,
-
     #[account(
         seeds = [
             pool.to_account_info().key.as_ref()
         ],
-        bump = pool_nonce,
+        bump = pool.load()?.nonce,
     )]
     pool_signer: UncheckedAccount<'info>,
+    token_program: Program<'info, Token>,
+}
 
+#[derive(Accounts)]
+pub struct CloseNftPool<'info> {
+    #[account(mut)]
+    refundee: UncheckedAccount<'info>,
     #[account(
-        zero,
+        mut,
+        constraint = reward_a_refundee.mint == pool.load()?.reward_a_mint @ ErrorCode::RewardARefundeeMintMismatch,
+        constraint = reward_a_refundee.owner == authority.key() @ ErrorCode::RewardARefundeeOwnerMismatch,
     )]
-    pool: Box<Account<'info, Pool>>,
-    
+    reward_a_refundee: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        close = refundee,
+        has_one = authority,
+        has_one = reward_a_vault,
+        constraint = pool.load()?.kind() == PoolKind::Nft @ ErrorCode::WrongPoolKind,
+        constraint = pool.load()?.is_paused(),
+        constraint = pool.load()?.user_stake_count == 0,
+        constraint = pool.load()?.total_staked == 0,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    authority: Signer<'info>,
+    #[account(mut,
+        constraint = reward_a_vault.owner == pool_signer.key(),
+    )]
+    reward_a_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        seeds = [
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = pool.load()?.nonce,
+    )]
+    pool_signer: UncheckedAccount<'info>,
     token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-#[instruction(nonce: u8)]
-pub struct CreateUser<'info> {
-    // Stake instance.
+#[instruction(nonce: u8, vault_nonce: u8)]
+pub struct CreateLock<'info> {
     #[account(
         mut,
-        constraint = !pool.paused,
+        constraint = !pool.load()?.is_paused(),
+        constraint = pool.load()?.kind() == PoolKind::Fungible @ ErrorCode::WrongPoolKind,
     )]
-    pool: Box<Account<'info, Pool>>,
-    // Member.
+    pool: AccountLoader<'info, Pool>,
+    #[account(
+        constraint = staking_mint.key() == pool.load()?.staking_mint,
+    )]
+    staking_mint: Box<Account<'info, Mint>>,
+    #[account(mut)]
+    owner: Signer<'info>,
+    #[account(
+        mut,
+        constraint = lock_from_account.mint == pool.load()?.staking_mint @ ErrorCode::StakeFromAccountMintMismatch,
+        constraint = lock_from_account.owner == owner.key() @ ErrorCode::StakeFromAccountOwnerMismatch,
+    )]
+    lock_from_account: Box<Account<'info, TokenAccount>>,
+
+    // Vote escrow.
     #[account(
         init,
         payer = owner,
         seeds = [
-            owner.key.as_ref(), 
-            pool.to_account_info().key.as_ref()
+            b"vote_escrow".as_ref(),
+            pool.to_account_info().key.as_ref(),
+            owner.key.as_ref()
         ],
         bump = nonce,
     )]
-    user: Box<Account<'info, User>>,
-    owner: Signer<'info>,
-    // Misc.
-    system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct Pause<'info> {
+    vote_escrow: Box<Account<'info, VoteEscrow>>,
     #[account(
-        mut, 
-        has_one = authority,
-        constraint = !pool.paused,
+        init,
+        payer = owner,
+        seeds = [
+            b"escrow_vault".as_ref(),
+            vote_escrow.key().as_ref()
+        ],
+        bump = vault_nonce,
+        token::mint = staking_mint,
+        token::authority = pool_signer,
     )]
-    pool: Box<Account<'info, Pool>>,
-    authority: Signer<'info>,
+    escrow_vault: Box<Account<'info, TokenAccount>>,
 
+    // Program signers.
     #[account(
         seeds = [
             pool.to_account_info().key.as_ref()
         ],
-        bump = pool.nonce,
+        bump = pool.load()?.nonce,
     )]
     pool_signer: UncheckedAccount<'info>,
+
+    // Misc.
     token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-pub struct Unpause<'info> {
+pub struct IncreaseLockAmount<'info> {
     #[account(
-        mut, 
-        has_one = authority,
-        constraint = pool.paused,
+        mut,
+        constraint = !pool.load()?.is_paused(),
     )]
-    pool: Box<Account<'info, Pool>>,
-    authority: Signer<'info>,
+    pool: AccountLoader<'info, Pool>,
+    owner: Signer<'info>,
+    #[account(
+        mut,
+        constraint = lock_from_account.mint == pool.load()?.staking_mint @ ErrorCode::StakeFromAccountMintMismatch,
+        constraint = lock_from_account.owner == owner.key() @ ErrorCode::StakeFromAccountOwnerMismatch,
+    )]
+    lock_from_account: Box<Account<'info, TokenAccount>>,
 
+    // Vote escrow.
     #[account(
+        mut,
+        has_one = owner,
+        has_one = pool,
         seeds = [
-            pool.to_account_info().key.as_ref()
+            b"vote_escrow".as_ref(),
+            pool.to_account_info().key.as_ref(),
+            owner.key.as_ref()
         ],
-        bump = pool.nonce,
+        bump = vote_escrow.nonce,
     )]
-    pool_signer: UncheckedAccount<'info>,
+    vote_escrow: Box<Account<'info, VoteEscrow>>,
+    #[account(
+        mut,
+        seeds = [
+            b"escrow_vault".as_ref(),
+            vote_escrow.key().as_ref()
+        ],
+        bump = vote_escrow.vault_nonce,
+    )]
+    escrow_vault: Box<Account<'info, TokenAccount>>,
+
+    // Misc.
     token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-pub struct Stake<'info> {
-    // Global accounts for the staking instance.
+pub struct ExtendLock<'info> {
+    #[account(mut)]
+    pool: AccountLoader<'info, Pool>,
+    owner: Signer<'info>,
     #[account(
-        mut, 
-        has_one = staking_vault,
+        mut,
+        has_one = owner,
+        has_one = pool,
+        seeds = [
+            b"vote_escrow".as_ref(),
+            pool.to_account_info().key.as_ref(),
+            owner.key.as_ref()
+        ],
+        bump = vote_escrow.nonce,
     )]
-    pool: Box<Account<'info, Pool>>,
+    vote_escrow: Box<Account<'info, VoteEscrow>>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawLock<'info> {
+    #[account(mut)]
+    pool: AccountLoader<'info, Pool>,
+    #[account(mut)]
+    owner: Signer<'info>,
     #[account(
         mut,
-        constraint = staking_vault.owner == *pool_signer.key,
+        constraint = lock_to_account.mint == pool.load()?.staking_mint @ ErrorCode::StakeFromAccountMintMismatch,
+        constraint = lock_to_account.owner == owner.key() @ ErrorCode::StakeFromAccountOwnerMismatch,
     )]
-    staking_vault: Box<Account<'info, TokenAccount>>,
+    lock_to_account: Box<Account<'info, TokenAccount>>,
 
-    // User.
+    // Vote escrow.
     #[account(
-        mut, 
-        has_one = owner, 
+        mut,
+        close = owner,
+        has_one = owner,
         has_one = pool,
         seeds = [
-            owner.key.as_ref(), 
-            pool.to_account_info().key.as_ref()
+            b"vote_escrow".as_ref(),
+            pool.to_account_info().key.as_ref(),
+            owner.key.as_ref()
         ],
-        bump = user.nonce,
+        bump = vote_escrow.nonce,
     )]
-    user: Box<Account<'info, User>>,
-    owner: Signer<'info>,
-    #[account(mut)]
-    stake_from_account: Box<Account<'info, TokenAccount>>,
+    vote_escrow: Box<Account<'info, VoteEscrow>>,
+    #[account(
+        mut,
+        seeds = [
+            b"escrow_vault".as_ref(),
+            vote_escrow.key().as_ref()
+        ],
+        bump = vote_escrow.vault_nonce,
+    )]
+    escrow_vault: Box<Account<'info, TokenAccount>>,
 
     // Program signers.
     #[account(
         seeds = [
             pool.to_account_info().key.as_ref()
         ],
-        bump = pool.nonce,
+        bump = pool.load()?.nonce,
     )]
     pool_signer: UncheckedAccount<'info>,
 
@@ -582,225 +4660,329 @@ pub struct Stake<'info> {
 }
 
 #[derive(Accounts)]
-pub struct Withdraw<'info> {
-    // Global accounts for the staking instance.
+pub struct UpdateVoterWeightRecord<'info> {
     #[account(
-        mut, 
-        has_one = staking_vault,
+        constraint = pool.load()?.kind() == PoolKind::Fungible @ ErrorCode::WrongPoolKind,
+        constraint = pool.load()?.governance_realm != Pubkey::default() @ ErrorCode::GovernanceRealmNotSet,
     )]
-    pool: Box<Account<'info, Pool>>,
+    pool: AccountLoader<'info, Pool>,
+    // Only a wallet's own user carries voting weight, positions and other pdas do not.
     #[account(
-        mut,
-        constraint = staking_vault.owner == *pool_signer.key,
+        has_one = pool,
+        seeds = [
+            user.load()?.owner.as_ref(),
+            pool.to_account_info().key.as_ref()
+        ],
+        bump = user.load()?.nonce,
     )]
-    staking_vault: Box<Account<'info, TokenAccount>>,
-
-    owner: Signer<'info>,
+    user: AccountLoader<'info, User>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = payer,
+        space = VoterWeightRecord::LEN,
+        seeds = [
+            b"voter_weight_record".as_ref(),
+            pool.to_account_info().key.as_ref(),
+            user.load()?.owner.as_ref()
+        ],
+        bump,
     )]
-    withdraw_account: Box<Account<'info, TokenAccount>>,
+    voter_weight_record: Box<Account<'info, VoterWeightRecord>>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    // Misc.
+    system_program: Program<'info, System>,
+}
 
-    // Program signers.
+#[derive(Accounts)]
+pub struct UpdateMaxVoterWeightRecord<'info> {
     #[account(
+        constraint = pool.load()?.kind() == PoolKind::Fungible @ ErrorCode::WrongPoolKind,
+        constraint = pool.load()?.governance_realm != Pubkey::default() @ ErrorCode::GovernanceRealmNotSet,
+    )]
+    pool: AccountLoader<'info, Pool>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = MaxVoterWeightRecord::LEN,
         seeds = [
+            b"max_voter_weight_record".as_ref(),
             pool.to_account_info().key.as_ref()
         ],
-        bump = pool.nonce,
+        bump,
     )]
-    pool_signer: UncheckedAccount<'info>,
+    max_voter_weight_record: Box<Account<'info, MaxVoterWeightRecord>>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    // Misc.
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u8)]
+pub struct CreateGaugeController<'info> {
+    #[account(
+        init,
+        payer = authority,
+    )]
+    controller: Box<Account<'info, GaugeController>>,
+    #[account(mut)]
+    authority: Signer<'info>,
+    // Pool whose vote escrow locks carry gauge votes.
+    #[account(
+        constraint = escrow_pool.load()?.kind() == PoolKind::Fungible @ ErrorCode::WrongPoolKind,
+    )]
+    escrow_pool: AccountLoader<'info, Pool>,
+
+    reward_a_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [
+            b"emission_vault".as_ref(),
+            controller.to_account_info().key.as_ref()
+        ],
+        bump,
+        token::mint = reward_a_mint,
+        token::authority = controller_signer,
+    )]
+    emission_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [
+            controller.to_account_info().key.as_ref()
+        ],
+        bump = nonce,
+    )]
+    controller_signer: UncheckedAccount<'info>,
 
     // Misc.
     token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-pub struct FunderChange<'info> {
-    // Global accounts for the staking instance.
+pub struct FundGaugeController<'info> {
     #[account(
-        mut, 
+        mut,
+        has_one = emission_vault,
+    )]
+    controller: Box<Account<'info, GaugeController>>,
+    #[account(mut)]
+    emission_vault: Box<Account<'info, TokenAccount>>,
+    funder: Signer<'info>,
+    #[account(mut)]
+    from: Box<Account<'info, TokenAccount>>,
+
+    // Misc.
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetGaugeEmission<'info> {
+    #[account(
+        mut,
         has_one = authority,
     )]
-    pool: Box<Account<'info, Pool>>,
+    controller: Box<Account<'info, GaugeController>>,
     authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct Fund<'info> {
-    // Global accounts for the staking instance.
+#[instruction(nonce: u8)]
+pub struct CreateGauge<'info> {
     #[account(
-        mut, 
-        has_one = reward_a_vault,
-        constraint = !pool.paused,
+        has_one = authority,
     )]
-    pool: Box<Account<'info, Pool>>,
+    controller: Box<Account<'info, GaugeController>>,
     #[account(mut)]
-    reward_a_vault: Box<Account<'info, TokenAccount>>,
+    authority: Signer<'info>,
+    // Checkpoints set the reward A rate, so the pool authority must run the controller.
     #[account(
-        //require signed funder auth - otherwise constant micro fund could hold funds hostage
-        constraint = funder.key() == pool.authority || pool.funders.iter().any(|x| *x == funder.key()),
+        has_one = authority,
+        constraint = pool.load()?.kind() == PoolKind::Fungible @ ErrorCode::WrongPoolKind,
+        constraint = pool.load()?.reward_a_mint == controller.reward_a_mint @ ErrorCode::RewardAccountMintMismatch,
     )]
-    funder: Signer<'info>,
-    #[account(mut)]
-    from_a: Box<Account<'info, TokenAccount>>,
-    
-    // Program signers.
+    pool: AccountLoader<'info, Pool>,
     #[account(
+        init,
+        payer = authority,
         seeds = [
+            b"gauge".as_ref(),
+            controller.key().as_ref(),
             pool.to_account_info().key.as_ref()
         ],
-        bump = pool.nonce,
+        bump = nonce,
     )]
-    pool_signer: UncheckedAccount<'info>,
-
+    gauge: Box<Account<'info, Gauge>>,
     // Misc.
-    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct FundStaking<'info> {
-    // Global accounts for the staking instance.
+#[instruction(voter_nonce: u8, vote_nonce: u8)]
+pub struct VoteGauge<'info> {
+    #[account(mut)]
+    controller: Box<Account<'info, GaugeController>>,
     #[account(
-        mut, 
-        has_one = staking_vault,
-        constraint = !pool.paused,
+        mut,
+        has_one = controller,
+    )]
+    gauge: Box<Account<'info, Gauge>>,
+    #[account(mut)]
+    owner: Signer<'info>,
+    #[account(
+        has_one = owner,
+        constraint = vote_escrow.pool == controller.escrow_pool @ ErrorCode::InvalidGauge,
     )]
-    pool: Box<Account<'info, Pool>>,
-    #[account(mut)]
-    staking_vault: Box<Account<'info, TokenAccount>>,
+    vote_escrow: Box<Account<'info, VoteEscrow>>,
     #[account(
-        //require signed funder auth - otherwise constant micro fund could hold funds hostage
-        constraint = funder.key() == pool.authority || pool.funders.iter().any(|x| *x == funder.key()),
+        init_if_needed,
+        payer = owner,
+        seeds = [
+            b"gauge_voter".as_ref(),
+            controller.key().as_ref(),
+            owner.key.as_ref()
+        ],
+        bump = voter_nonce,
     )]
-    funder: Signer<'info>,
-    #[account(mut)]
-    from: Box<Account<'info, TokenAccount>>,
-    
-    // Program signers.
+    voter: Box<Account<'info, GaugeVoter>>,
     #[account(
+        init_if_needed,
+        payer = owner,
         seeds = [
-            pool.to_account_info().key.as_ref()
+            b"gauge_vote".as_ref(),
+            gauge.key().as_ref(),
+            owner.key.as_ref()
         ],
-        bump = pool.nonce,
+        bump = vote_nonce,
     )]
-    pool_signer: UncheckedAccount<'info>,
-
+    gauge_vote: Box<Account<'info, GaugeVote>>,
     // Misc.
-    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ClaimReward<'info> {
-    // Global accounts for the staking instance.
-    #[account(
-        mut, 
-        has_one = staking_vault,
-        has_one = reward_a_vault,
-    )]
-    pool: Box<Account<'info, Pool>>,
-    #[account(mut)]
-    staking_vault: Box<Account<'info, TokenAccount>>,
-    #[account(mut)]
-    reward_a_vault: Box<Account<'info, TokenAccount>>,
-
-    // User.
+pub struct CheckpointGauges<'info> {
     #[account(
         mut,
-        has_one = owner,
-        has_one = pool,
-        seeds = [
-            owner.to_account_info().key.as_ref(),
-            pool.to_account_info().key.as_ref()
-        ],
-        bump = user.nonce,
+        has_one = emission_vault,
     )]
-    user: Box<Account<'info, User>>,
-    owner: Signer<'info>,
-    #[account(mut)]
-    reward_a_account: Box<Account<'info, TokenAccount>>,
+    controller: Box<Account<'info, GaugeController>>,
     #[account(mut)]
-    reward_b_account: Box<Account<'info, TokenAccount>>,
+    emission_vault: Box<Account<'info, TokenAccount>>,
 
     // Program signers.
     #[account(
         seeds = [
-            pool.to_account_info().key.as_ref()
+            controller.to_account_info().key.as_ref()
         ],
-        bump = pool.nonce,
+        bump = controller.nonce,
     )]
-    pool_signer: UncheckedAccount<'info>,
+    controller_signer: UncheckedAccount<'info>,
 
     // Misc.
     token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-pub struct CloseUser<'info> {
-    #[account(
-        mut, 
-    )]
-    pool: Box<Account<'info, Pool>>,
+pub struct PoolView<'info> {
+    pool: AccountLoader<'info, Pool>,
+}
+
+#[derive(Accounts)]
+pub struct UserView<'info> {
+    pool: AccountLoader<'info, Pool>,
     #[account(
-        mut,
-        close = owner,
-        has_one = owner,
         has_one = pool,
-        seeds = [
-            owner.to_account_info().key.as_ref(),
-            pool.to_account_info().key.as_ref()
-        ],
-        bump = user.nonce,
-        constraint = user.balance_staked == 0,
-        constraint = user.reward_a == 0,
-        constraint = user.reward_b == 0,
     )]
-    user: Account<'info, User>,
-    owner: Signer<'info>,
+    user: AccountLoader<'info, User>,
 }
 
 #[derive(Accounts)]
-pub struct ClosePool<'info> {
-    #[account(mut)]
-    refundee: UncheckedAccount<'info>,
-    #[account(mut)]
-    staking_refundee: Box<Account<'info, TokenAccount>>,
-    #[account(mut)]
-    reward_a_refundee: Box<Account<'info, TokenAccount>>,
-    #[account(
-        mut,
-        close = refundee,
-        has_one = authority,
-        has_one = staking_vault,
-        has_one = reward_a_vault,
-        constraint = pool.paused,
-        constraint = pool.user_stake_count == 0,
-    )]
-    pool: Account<'info, Pool>,
-    authority: Signer<'info>,
-    #[account(mut,
-        constraint = staking_vault.amount == 0,
-    )]
-    staking_vault: Box<Account<'info, TokenAccount>>,
-    #[account(mut)]
-    reward_a_vault: Box<Account<'info, TokenAccount>>,
-    #[account(
-        seeds = [
-            pool.to_account_info().key.as_ref()
-        ],
-        bump = pool.nonce,
-    )]
-    pool_signer: UncheckedAccount<'info>,
-    token_program: Program<'info, Token>,
+pub struct VoteEscrowView<'info> {
+    vote_escrow: Box<Account<'info, VoteEscrow>>,
 }
 
-#[account]
+/// Returned by `get_pending_rewards`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct PendingRewards {
+    /// Reward A claimable now.
+    pub reward_a: u64,
+    /// Reward B claimable now.
+    pub reward_b: u64,
+    /// Each extra reward claimable now, in pool order.
+    pub extra_rewards: [u64; MAX_EXTRA_REWARDS],
+}
+
+/// Returned by `get_pool_summary`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct PoolSummary {
+    pub paused: bool,
+    pub user_stake_count: u32,
+    pub total_staked: u64,
+    pub reward_a_rate: u64,
+    /// Reward A per token accrued up to now, scaled by `PRECISION`.
+    pub reward_a_per_token_stored: u128,
+    pub period_finish: u64,
+    pub nft_reward_rate: u64,
+    pub total_rewards_a_paid: u64,
+    pub total_rewards_b_paid: u64,
+    pub total_funded_a: u64,
+    pub total_funded_b: u64,
+    pub last_activity_time: u64,
+}
+
+/// Returned by `get_unlock_time`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct UnlockTime {
+    /// The last stake time.
+    pub stake_time: u64,
+    /// The first time the stake can be withdrawn.
+    pub unlock_time: u64,
+    /// Whether `unlock_time` has passed.
+    pub unlocked: bool,
+}
+
+/// Returned by `get_voting_power`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct VotingPower {
+    /// Tokens locked.
+    pub amount: u64,
+    /// The time the lock ends.
+    pub lock_end: u64,
+    /// Voting power at the requested time.
+    pub voting_power: u64,
+}
+
+/// Returned by `get_remaining_capacity`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct RemainingCapacity {
+    /// Cap on the pool total, zero for no cap.
+    pub max_total_staked: u64,
+    /// Cap per user, zero for no cap.
+    pub max_per_user: u64,
+    /// How much more the user can stake, `u64::MAX` if uncapped.
+    pub remaining: u64,
+}
+
+/// Pools and users are zero-copy. `#[account(zero_copy)]` packs them with no
+/// padding and `repr(C)` pins the field order, so they are laid out byte for
+/// byte like the Borsh encoding they were first written with and existing
+/// accounts load in place. Flags and enums are stored as `u8` because any
+/// byte is a valid `u8`, read them through the accessors.
+/// Accounts from before the versioned layout grow through `migrate_pool`.
+#[account(zero_copy)]
+#[derive(Default)]
+#[repr(C)]
 pub struct Pool {
     /// Priviledged account.
     pub authority: Pubkey,
     /// Nonce to derive the program-derived address owning the vaults.
     pub nonce: u8,
-    /// Paused state of the program
-    pub paused: bool,
+    /// Paused state of the program, see `is_paused`.
+    pub paused: u8,
     /// Mint of the token that can be staked.
     pub staking_mint: Pubkey,
     /// Vault to store staked tokens.
@@ -815,14 +4997,318 @@ pub struct Pool {
     /// [] because short size, fixed account size, and ease of use on 
     /// client due to auto generated account size property
     pub funders: [Pubkey; 5],
+    /// Whether `stake_for` deposits signed by the beneficiary restart their unstake lock.
+    pub stake_for_resets_lock: u8,
+    /// Whether stake positions can be moved between users with `transfer_position`.
+    pub transfers_enabled: u8,
+    /// What kind of asset the pool stakes, see `kind()`.
+    pub kind: u8,
+    /// Verified collection nfts must belong to, for nft pools.
+    pub nft_collection: Pubkey,
+    /// Reward A emitted per staked nft per second, for nft pools.
+    pub nft_reward_rate: u64,
+    /// Tokens currently staked by all users, or nfts for nft pools.
+    pub total_staked: u64,
+    /// Reward A emitted per second during the current campaign.
+    pub reward_a_rate: u64,
+    /// Reward A accumulated per staked token, scaled by `PRECISION`.
+    pub reward_a_per_token_stored: u128,
+    /// The last time the reward A accumulator was updated.
+    pub last_update_time: u64,
+    /// The time the current reward A campaign ends.
+    pub period_finish: u64,
+    /// Additional reward tokens, empty slots have a default mint.
+    pub extra_rewards: [ExtraReward; MAX_EXTRA_REWARDS],
+    /// Layout version, see `migrate_pool`.
+    pub version: u8,
+    /// Reward A paid out to users.
+    pub total_rewards_a_paid: u64,
+    /// Reward B paid out to users.
+    pub total_rewards_b_paid: u64,
+    /// Reward A deposited by funders.
+    pub total_funded_a: u64,
+    /// Staking tokens deposited by funders to pay reward B.
+    pub total_funded_b: u64,
+    /// The last time an instruction changed the pool.
+    pub last_activity_time: u64,
+    /// Cap on `total_staked`, zero for no cap.
+    pub max_total_staked: u64,
+    /// Cap on a single user's stake, zero for no cap.
+    pub max_per_user: u64,
+    /// Merkle root of wallets allowed to `create_user`, zeroed for an open pool.
+    pub allowlist_root: [u8; 32],
+    /// Staking tokens held in vote escrow locks.
+    pub total_locked: u64,
+    /// SPL Governance realm voter weight records are written for, default for none.
+    /// Stake cannot leave a user with votes outstanding, see `check_no_outstanding_votes`.
+    pub governance_realm: Pubkey,
+    /// Sum of users' working balances, reward A is split by these.
+    pub working_supply: u64,
+    /// Pool whose stakers earn boosted reward A here, default for no boosts.
+    pub boost_pool: Pubkey,
+    /// Staking time per loyalty step, zero for no loyalty bonus.
+    pub loyalty_step_duration: u64,
+    /// Reward A bonus per loyalty step, in basis points.
+    pub loyalty_step_bps: u64,
+    /// Cap on the loyalty bonus, in basis points.
+    pub loyalty_max_bps: u64,
+    /// Stake needed for bronze, silver, gold and platinum, all zero for no tiers.
+    pub tier_thresholds: [u64; TIER_COUNT],
+    /// Annual reward B rate of each tier, in basis points of the stake.
+    pub tier_reward_b_bps: [u64; TIER_COUNT],
+    /// Referrers earn this share of their referees' reward A on top, in basis points.
+    pub referral_bps: u64,
+    /// Cap on `total_referral_paid`, zero for no cap.
+    pub referral_payout_cap: u64,
+    /// Reward A credited to referrers.
+    pub total_referral_paid: u64,
+    /// Time after a claim before any of it releases.
+    pub vesting_cliff: u64,
+    /// Time over which claimed reward A releases linearly, zero to pay claims out directly.
+    pub vesting_duration: u64,
+    /// Share of reward A given up by `claim_instant`, in basis points.
+    pub instant_claim_haircut_bps: u64,
+    /// Signer allowed to `slash` the pool, default for none.
+    pub slasher: Pubkey,
+    /// Cap on a single slash, in basis points of `total_staked`.
+    pub max_slash_bps: u64,
+    /// Staked tokens per staked share, scaled by `SLASH_INDEX_PRECISION`, falls with each slash.
+    pub slash_index: u64,
+    /// Staked tokens taken by slashes.
+    pub total_slashed: u64,
+    /// Wait between `start_cooldown` and unstaking, zero for no cooldown.
+    pub cooldown_duration: u64,
+    /// How long after the cooldown unstaking stays open.
+    pub unstake_window: u64,
+    /// Lock after each deposit, zero for the default `UNSTAKE_LOCK_PERIOD`.
+    pub unstake_lock_period: u64,
+    /// Reward A funded for referral credits, held in the reward A vault apart from emissions.
+    pub referral_reserve: u64,
+    /// Reward A emitted to stakers but not yet paid out, campaigns must be funded on top.
+    pub reward_a_owed: u64,
+    /// Each extra reward emitted but not yet paid out, in the order of `extra_rewards`.
+    pub extra_rewards_owed: [u64; MAX_EXTRA_REWARDS],
+    /// Space for future fields.
+    pub reserved: [u64; 10],
+}
+
+impl Pool {
+    /// Account size of the current layout, discriminator included.
+    pub fn space() -> usize {
+        8 + std::mem::size_of::<Pool>()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused != 0
+    }
+
+    pub fn stake_for_resets_lock(&self) -> bool {
+        self.stake_for_resets_lock != 0
+    }
+
+    pub fn transfers_enabled(&self) -> bool {
+        self.transfers_enabled != 0
+    }
+
+    pub fn kind(&self) -> PoolKind {
+        match self.kind {
+            1 => PoolKind::Nft,
+            _ => PoolKind::Fungible,
+        }
+    }
+
+    /// Stamps the pool with the current time.
+    pub fn record_activity(&mut self) {
+        self.last_activity_time = clock::Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+    }
+
+    /// Whether reward B follows `tier_thresholds` instead of the flat threshold.
+    pub fn has_tiers(&self) -> bool {
+        let thresholds = self.tier_thresholds;
+        thresholds.iter().any(|threshold| *threshold > 0)
+    }
+
+    /// Lock after each deposit before the stake can be withdrawn.
+    pub fn unstake_lock_period(&self) -> u64 {
+        if self.unstake_lock_period == 0 {
+            UNSTAKE_LOCK_PERIOD
+        } else {
+            self.unstake_lock_period
+        }
+    }
+
+    /// Whether claims vest instead of paying reward A out directly.
+    pub fn has_vesting(&self) -> bool {
+        self.vesting_duration > 0
+    }
+
+    /// Reward A vault balance left once the referral reserve is held back.
+    pub fn reward_a_available(&self, vault_amount: u64) -> u64 {
+        vault_amount.saturating_sub(self.referral_reserve)
+    }
+
+    /// Staking vault balance beyond the stake it holds, reward B never pays out of principal.
+    pub fn reward_b_available(&self, vault_amount: u64) -> u64 {
+        vault_amount.saturating_sub(self.total_staked)
+    }
+
+    /// Whether `create_user` requires an allowlist proof.
+    pub fn has_allowlist(&self) -> bool {
+        self.allowlist_root != [0; 32]
+    }
+
+    /// Fails if the pool or `user` is over its cap.
+    pub fn check_deposit_caps(&self, user: &User) -> Result<()> {
+        if self.max_total_staked > 0 && self.total_staked > self.max_total_staked {
+            return Err(ErrorCode::MaxTotalStakedExceeded.into());
+        }
+        if self.max_per_user > 0 && user.balance_staked > self.max_per_user {
+            return Err(ErrorCode::MaxPerUserExceeded.into());
+        }
+        if user.allowlist_cap > 0 && user.balance_staked > user.allowlist_cap {
+            return Err(ErrorCode::MaxPerUserExceeded.into());
+        }
+
+        Ok(())
+    }
+
+    /// Fails unless `user` is inside the unstake window of its cooldown.
+    pub fn check_cooldown(&self, user: &User, timestamp: u64) -> Result<()> {
+        if self.cooldown_duration == 0 {
+            return Ok(());
+        }
+        let cooldown_end = user.cooldown_start.checked_add(self.cooldown_duration).unwrap();
+        if user.cooldown_start == 0 || timestamp < cooldown_end {
+            return Err(ErrorCode::CooldownNotElapsed.into());
+        }
+        if timestamp > cooldown_end.checked_add(self.unstake_window).unwrap() {
+            return Err(ErrorCode::UnstakeWindowClosed.into());
+        }
+
+        Ok(())
+    }
+
+    /// How much more `user` can stake, `u64::MAX` if uncapped.
+    pub fn remaining_capacity(&self, user: &User) -> u64 {
+        let mut remaining = u64::MAX;
+        if self.max_total_staked > 0 {
+            remaining = remaining.min(self.max_total_staked.saturating_sub(self.total_staked));
+        }
+        if self.max_per_user > 0 {
+            remaining = remaining.min(self.max_per_user.saturating_sub(user.balance_staked));
+        }
+        if user.allowlist_cap > 0 {
+            remaining = remaining.min(user.allowlist_cap.saturating_sub(user.balance_staked));
+        }
+        remaining
+    }
+}
+
+/// Maximum number of extra reward tokens per pool.
+pub const MAX_EXTRA_REWARDS: usize = 4;
+
+#[zero_copy]
+#[derive(Default)]
+#[repr(C)]
+pub struct ExtraReward {
+    /// Mint of the reward token.
+    pub mint: Pubkey,
+    /// Vault to store reward tokens.
+    pub vault: Pubkey,
+    /// Reward emitted per second during the current campaign.
+    pub reward_rate: u64,
+    /// Reward accumulated per staked token, scaled by `PRECISION`.
+    pub reward_per_token_stored: u128,
+    /// The last time the accumulator was updated.
+    pub last_update_time: u64,
+    /// The time the current campaign ends.
+    pub period_finish: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum PoolKind {
+    /// Stakes `staking_mint` tokens.
+    Fungible,
+    /// Stakes nfts of `nft_collection`.
+    Nft,
+}
+
+//the derive(Default) enum attribute is newer than the bpf toolchain
+#[allow(clippy::derivable_impls)]
+impl Default for PoolKind {
+    fn default() -> Self {
+        PoolKind::Fungible
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Debug)]
+#[repr(u8)]
+pub enum Tier {
+    /// Below the bronze threshold, or the pool has no tiers.
+    None,
+    Bronze,
+    Silver,
+    Gold,
+    Platinum,
+}
+
+//the derive(Default) enum attribute is newer than the bpf toolchain
+#[allow(clippy::derivable_impls)]
+impl Default for Tier {
+    fn default() -> Self {
+        Tier::None
+    }
+}
+
+#[event]
+pub struct TierChanged {
+    pub pool: Pubkey,
+    /// The user owner, or the position nft mint for nft positions.
+    pub owner: Pubkey,
+    pub old_tier: Tier,
+    pub new_tier: Tier,
+    pub balance_staked: u64,
 }
 
+#[event]
+pub struct Slashed {
+    pub pool: Pubkey,
+    pub slasher: Pubkey,
+    /// Token account the slashed stake was sent to.
+    pub destination: Pubkey,
+    pub amount: u64,
+    /// Stake left in the pool after the slash.
+    pub total_staked: u64,
+    pub slash_index: u64,
+    pub timestamp: i64,
+}
+
+/// Maximum number of pools listed in the registry.
+pub const MAX_REGISTERED_POOLS: usize = 128;
+
 #[account]
 #[derive(Default)]
+pub struct PoolRegistry {
+    /// Account allowed to create pools through the registry.
+    pub authority: Pubkey,
+    /// Signer nonce.
+    pub nonce: u8,
+    /// Pools created through the registry, in creation order.
+    pub pools: Vec<Pubkey>,
+}
+
+impl PoolRegistry {
+    pub const LEN: usize = 8 + 32 + 1 + 4 + 32 * MAX_REGISTERED_POOLS;
+}
+
+#[account(zero_copy)]
+#[derive(Default)]
+#[repr(C)]
 pub struct User {
     /// Pool the this user belongs to.
     pub pool: Pubkey,
-    /// The owner of this account.
+    /// The owner of this account, or the position nft mint for nft positions.
     pub owner: Pubkey,
     /// The amount of token A claimed.
     pub reward_a: u64,
@@ -840,6 +5326,268 @@ pub struct User {
     pub stake_time: u64,
     /// Signer nonce.
     pub nonce: u8,
+    /// Reward A per token already credited to this user.
+    pub reward_a_per_token_complete: u128,
+    /// The amount of each extra reward pending claim.
+    pub extra_rewards: [u64; MAX_EXTRA_REWARDS],
+    /// Extra reward per token already credited to this user.
+    pub extra_reward_per_token_complete: [u128; MAX_EXTRA_REWARDS],
+    /// Layout version, see `migrate_user`.
+    pub version: u8,
+    /// Per wallet cap proven when joining an allowlisted pool, zero for no cap.
+    pub allowlist_cap: u64,
+    /// Stake reward A is paid on, see `working_balance`.
+    pub working_balance: u64,
+    /// Share of the boost pool staked by the owner, scaled by `BOOST_SHARE_PRECISION`.
+    pub boost_share: u64,
+    /// Start of continuous staking for the loyalty bonus, see `reset_loyalty`.
+    pub loyalty_start: u64,
+    /// Tier as of the last reward update, see `refresh_tier` and `tier()`.
+    pub tier: u8,
+    /// User account that referred this one, default for none.
+    pub referrer: Pubkey,
+    /// Referrer share accrued on this user's reward A and not yet credited.
+    pub referral_pending: u64,
+    /// Users referred by this one.
+    pub referral_count: u64,
+    /// Reward A credited to this user for referrals.
+    pub referral_earned: u64,
+    /// Reward A on the current vesting schedule.
+    pub vesting_total: u64,
+    /// Part of `vesting_total` already released.
+    pub vesting_released: u64,
+    /// Reward A vested on earlier schedules and not yet released.
+    pub vesting_unlocked: u64,
+    /// Start of the current vesting schedule.
+    pub vesting_start: u64,
+    /// Nothing on the current schedule releases before this.
+    pub vesting_cliff_end: u64,
+    /// Everything on the current schedule has vested by this.
+    pub vesting_end: u64,
+    /// Pool slash index `balance_staked` was last scaled to.
+    pub slash_index: u64,
+    /// Start of the unstake cooldown, zero for none.
+    pub cooldown_start: u64,
+    /// Space for future fields.
+    pub reserved: [u64; 8],
+}
+
+impl User {
+    /// Account size of the current layout, discriminator included.
+    pub fn space() -> usize {
+        8 + std::mem::size_of::<User>()
+    }
+
+    pub fn tier(&self) -> Tier {
+        match self.tier {
+            1 => Tier::Bronze,
+            2 => Tier::Silver,
+            3 => Tier::Gold,
+            4 => Tier::Platinum,
+            _ => Tier::None,
+        }
+    }
+
+    /// Whether every extra reward has been claimed.
+    pub fn extra_rewards_settled(&self) -> bool {
+        let extra_rewards = self.extra_rewards;
+        extra_rewards.iter().all(|x| *x == 0)
+    }
+
+    /// `balance_staked` with slashes not yet picked up by `update_rewards` applied.
+    pub fn current_balance_staked(&self, pool: &Pool) -> u64 {
+        //nothing has been slashed before the pool's slashing upgrade
+        if pool.slash_index == 0 {
+            return self.balance_staked;
+        }
+        let slash_index = if self.slash_index == 0 { SLASH_INDEX_PRECISION } else { self.slash_index };
+        apply_slash_index(self.balance_staked, slash_index, pool.slash_index)
+    }
+
+    /// Whether all vesting reward A has been released.
+    pub fn vesting_settled(&self) -> bool {
+        self.vesting_unlocked == 0 && self.vesting_released == self.vesting_total
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct StakedNft {
+    /// Pool the nft is staked in.
+    pub pool: Pubkey,
+    /// The owner the nft is returned to.
+    pub owner: Pubkey,
+    /// Mint of the staked nft.
+    pub mint: Pubkey,
+    /// The time the nft was staked.
+    pub stake_time: u64,
+    /// Signer nonce.
+    pub nonce: u8,
+    /// Escrow vault nonce.
+    pub vault_nonce: u8,
+}
+
+/// Splits a reward A emission budget across pools by gauge votes.
+#[account]
+#[derive(Default)]
+pub struct GaugeController {
+    /// Account allowed to add gauges and change the emission.
+    pub authority: Pubkey,
+    /// Pool whose vote escrow locks carry gauge votes.
+    pub escrow_pool: Pubkey,
+    /// Mint emitted as reward A to every gauged pool.
+    pub reward_a_mint: Pubkey,
+    /// Vault holding the emission budget, paid out at checkpoints.
+    pub emission_vault: Pubkey,
+    /// Nonce of the controller signer owning `emission_vault`.
+    pub nonce: u8,
+    /// Reward A deposited into `emission_vault` so far.
+    pub total_funded: u64,
+    /// Reward A emitted across all gauged pools per epoch.
+    pub emission_per_epoch: u64,
+    /// Epoch length in seconds.
+    pub epoch_duration: u64,
+    /// The time epoch zero started.
+    pub genesis_time: u64,
+    /// Epoch `total_weight` belongs to.
+    pub epoch: u64,
+    /// Votes cast during `epoch`.
+    pub total_weight: u64,
+    /// Votes cast during the epoch before `epoch`.
+    pub last_total_weight: u64,
+}
+
+/// Votes directing emissions to a pool.
+#[account]
+#[derive(Default)]
+pub struct Gauge {
+    /// Controller the gauge draws from.
+    pub controller: Pubkey,
+    /// Pool whose reward A rate the gauge sets.
+    pub pool: Pubkey,
+    /// Epoch `weight` belongs to.
+    pub epoch: u64,
+    /// Votes cast during `epoch`.
+    pub weight: u64,
+    /// Votes cast during the epoch before `epoch`.
+    pub last_weight: u64,
+    /// The last epoch the pool rate was set in.
+    pub checkpoint_epoch: u64,
+    /// Whether the pool rate has been set at all.
+    pub checkpointed: bool,
+    /// Signer nonce.
+    pub nonce: u8,
+}
+
+/// A voter's allocation across gauges.
+#[account]
+#[derive(Default)]
+pub struct GaugeVoter {
+    /// Controller voted on.
+    pub controller: Pubkey,
+    /// The voter.
+    pub owner: Pubkey,
+    /// Epoch `used_bps` belongs to.
+    pub epoch: u64,
+    /// Share of voting power already placed during `epoch`.
+    pub used_bps: u16,
+    /// Signer nonce.
+    pub nonce: u8,
+}
+
+/// A voter's vote on one gauge.
+#[account]
+#[derive(Default)]
+pub struct GaugeVote {
+    /// Gauge voted for.
+    pub gauge: Pubkey,
+    /// The voter.
+    pub owner: Pubkey,
+    /// Epoch the vote was cast in.
+    pub epoch: u64,
+    /// Share of voting power placed.
+    pub bps: u16,
+    /// Voting power placed.
+    pub power: u64,
+    /// Signer nonce.
+    pub nonce: u8,
+}
+
+/// Voter weight of a staker in the SPL Governance addin format. The name gives
+/// the discriminator governance expects, `sha256("account:VoterWeightRecord")[..8]`.
+#[account]
+#[derive(Default)]
+pub struct VoterWeightRecord {
+    /// Realm the weight is for.
+    pub realm: Pubkey,
+    /// Governing mint of the realm, the pool staking mint.
+    pub governing_token_mint: Pubkey,
+    /// The staker.
+    pub governing_token_owner: Pubkey,
+    /// Tokens staked.
+    pub voter_weight: u64,
+    /// Slot the weight was computed in, stale afterwards.
+    pub voter_weight_expiry: Option<u64>,
+    /// Action the weight is restricted to, none for any.
+    pub weight_action: Option<VoterWeightAction>,
+    /// Target the weight is restricted to, none for any.
+    pub weight_action_target: Option<Pubkey>,
+    /// Reserved by the addin format.
+    pub reserved: [u8; 8],
+}
+
+impl VoterWeightRecord {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 8 + (1 + 8) + (1 + 1) + (1 + 32) + 8;
+}
+
+/// Total voter weight of a pool in the SPL Governance addin format.
+#[account]
+#[derive(Default)]
+pub struct MaxVoterWeightRecord {
+    /// Realm the weight is for.
+    pub realm: Pubkey,
+    /// Governing mint of the realm, the pool staking mint.
+    pub governing_token_mint: Pubkey,
+    /// Tokens staked in the pool.
+    pub max_voter_weight: u64,
+    /// Slot the weight was computed in, stale afterwards.
+    pub max_voter_weight_expiry: Option<u64>,
+    /// Reserved by the addin format.
+    pub reserved: [u8; 8],
+}
+
+impl MaxVoterWeightRecord {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + (1 + 8) + 8;
+}
+
+/// Governance actions a voter weight can be restricted to.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum VoterWeightAction {
+    CastVote,
+    CommentProposal,
+    CreateGovernance,
+    CreateProposal,
+    SignOffProposal,
+}
+
+/// Staking tokens locked for voting power, see `voting_power`.
+#[account]
+#[derive(Default)]
+pub struct VoteEscrow {
+    /// Pool whose staking tokens are locked.
+    pub pool: Pubkey,
+    /// The owner of the lock.
+    pub owner: Pubkey,
+    /// Tokens locked.
+    pub amount: u64,
+    /// The time the lock was created.
+    pub lock_start: u64,
+    /// The time the tokens can be withdrawn, only ever pushed out.
+    pub lock_end: u64,
+    /// Signer nonce.
+    pub nonce: u8,
+    /// Escrow vault nonce.
+    pub vault_nonce: u8,
 }
 
 #[error]
@@ -864,4 +5612,128 @@ pub enum ErrorCode {
     CannotDeauthorizeMissingAuthority,
     #[msg("Can unstake only after 30 days more.")]
     NotEnoughUnstakePeriod,
+    #[msg("Stake account mint does not match the pool staking mint.")]
+    StakeFromAccountMintMismatch,
+    #[msg("Stake account is not owned by the user owner.")]
+    StakeFromAccountOwnerMismatch,
+    #[msg("Reward A account is not the owner's associated token account.")]
+    RewardAAccountNotAssociated,
+    #[msg("Reward A account mint does not match the pool reward A mint.")]
+    RewardAAccountMintMismatch,
+    #[msg("Reward A account is not owned by the user owner.")]
+    RewardAAccountOwnerMismatch,
+    #[msg("Reward B account is not the owner's associated token account.")]
+    RewardBAccountNotAssociated,
+    #[msg("Reward B account mint does not match the pool staking mint.")]
+    RewardBAccountMintMismatch,
+    #[msg("Reward B account is not owned by the user owner.")]
+    RewardBAccountOwnerMismatch,
+    #[msg("Staking refundee mint does not match the pool staking mint.")]
+    StakingRefundeeMintMismatch,
+    #[msg("Staking refundee is not owned by the pool authority.")]
+    StakingRefundeeOwnerMismatch,
+    #[msg("Reward A refundee mint does not match the pool reward A mint.")]
+    RewardARefundeeMintMismatch,
+    #[msg("Reward A refundee is not owned by the pool authority.")]
+    RewardARefundeeOwnerMismatch,
+    #[msg("Insufficient funds to transfer.")]
+    InsufficientFundTransfer,
+    #[msg("Position transfers are disabled for this pool.")]
+    PositionTransfersDisabled,
+    #[msg("Cannot transfer a position to its own owner.")]
+    CannotTransferToSelf,
+    #[msg("Signer does not hold the position nft.")]
+    NotPositionHolder,
+    #[msg("Instruction is not supported for this kind of pool.")]
+    WrongPoolKind,
+    #[msg("Nft is not a verified member of the pool collection.")]
+    NftNotInCollection,
+    #[msg("Reward token is already distributed by this pool.")]
+    RewardAlreadyAdded,
+    #[msg("Maximum reward tokens already added.")]
+    MaxRewards,
+    #[msg("Reward token not found.")]
+    RewardNotFound,
+    #[msg("Reward vault does not match the pool.")]
+    InvalidRewardVault,
+    #[msg("Missing reward vault and destination accounts.")]
+    MissingRewardAccounts,
+    #[msg("Reward account mint does not match the reward mint.")]
+    RewardAccountMintMismatch,
+    #[msg("Reward account is not owned by the recipient.")]
+    RewardAccountOwnerMismatch,
+    #[msg("Pool registry is full.")]
+    RegistryFull,
+    #[msg("Stake would exceed the pool deposit cap.")]
+    MaxTotalStakedExceeded,
+    #[msg("Stake would exceed the per user deposit cap.")]
+    MaxPerUserExceeded,
+    #[msg("Wallet is not on the pool allowlist.")]
+    InvalidAllowlistProof,
+    #[msg("Allowlisted pools only admit new users through create_user.")]
+    AllowlistProofRequired,
+    #[msg("Lock must end in the future and within the maximum lock duration.")]
+    InvalidLockEnd,
+    #[msg("Lock can only be extended.")]
+    LockCannotBeShortened,
+    #[msg("Lock has expired.")]
+    LockExpired,
+    #[msg("Lock has not expired yet.")]
+    LockNotExpired,
+    #[msg("Pool has no governance realm.")]
+    GovernanceRealmNotSet,
+    #[msg("Gauge votes exceed the voter's power.")]
+    GaugeVoteOverAllocated,
+    #[msg("Gauge does not belong to the controller or pool.")]
+    InvalidGauge,
+    #[msg("Missing gauge, pool and reward vault accounts.")]
+    MissingGaugeAccounts,
+    #[msg("Epoch duration must be positive.")]
+    InvalidEpochDuration,
+    #[msg("Pool has no boost pool.")]
+    BoostNotEnabled,
+    #[msg("Tier thresholds must start above zero and increase.")]
+    InvalidTierThresholds,
+    #[msg("Referrer is not a user of this pool.")]
+    InvalidReferrer,
+    #[msg("Referral share cannot exceed 100%.")]
+    InvalidReferralBps,
+    #[msg("Vesting cliff cannot exceed the vesting duration.")]
+    InvalidVestingSchedule,
+    #[msg("Instant claim haircut cannot exceed 100%.")]
+    InvalidHaircutBps,
+    #[msg("Signer is not the pool's slasher.")]
+    InvalidSlasher,
+    #[msg("Slash cap must be below 100%.")]
+    InvalidSlashBps,
+    #[msg("Slash exceeds the pool's slash cap.")]
+    SlashTooLarge,
+    #[msg("Slash destination must hold the staking mint.")]
+    SlashDestinationMintMismatch,
+    #[msg("Unstake window must be positive when a cooldown is set.")]
+    InvalidUnstakeWindow,
+    #[msg("Unstake cooldown has not elapsed.")]
+    CooldownNotElapsed,
+    #[msg("Unstake window has closed, start a new cooldown.")]
+    UnstakeWindowClosed,
+    #[msg("A slasher needs an unstake cooldown.")]
+    SlasherRequiresCooldown,
+    #[msg("Unstake lock cannot exceed the default lock.")]
+    InvalidUnstakeLockPeriod,
+    #[msg("Signer is not the program upgrade authority.")]
+    NotUpgradeAuthority,
+    #[msg("Governance realm or token owner record does not match the pool.")]
+    GovernanceAccountsMismatch,
+    #[msg("Stake is counted in a governance vote, relinquish it first.")]
+    VotesOutstanding,
+    #[msg("Positions cannot claim while the pool vests rewards.")]
+    PositionVestingUnsupported,
+    #[msg("User does not belong to the pool.")]
+    UserPoolMismatch,
+    #[msg("Moved stake is locked or cooling down longer than the recipient's stake.")]
+    TransferOutlastsRecipient,
+    #[msg("Reward rate is more than the vault can pay over the duration.")]
+    RewardTooHigh,
+    #[msg("Tier reward b rates cannot exceed the stake each year.")]
+    InvalidTierRewardRate,
 }
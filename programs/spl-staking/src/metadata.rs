@@ -0,0 +1,167 @@
+//! Minimal bindings for the Metaplex token metadata program.
+//!
+//! Only the pieces the pool needs are mirrored here, so the program does not
+//! have to pull in the full metadata crate and its solana version.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+
+anchor_lang::solana_program::declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/// Seed prefix of metadata accounts.
+pub const PREFIX: &[u8] = b"metadata";
+
+/// Instruction index of `CreateMetadataAccountV3`.
+const CREATE_METADATA_ACCOUNT_V3: u8 = 33;
+
+/// Instruction index of `UpdateMetadataAccountV2`.
+const UPDATE_METADATA_ACCOUNT_V2: u8 = 15;
+
+pub fn find_metadata_account(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PREFIX, ID.as_ref(), mint.as_ref()], &ID)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Creator {
+    pub address: Pubkey,
+    pub verified: bool,
+    pub share: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Collection {
+    pub verified: bool,
+    pub key: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Uses {
+    pub use_method: u8,
+    pub remaining: u64,
+    pub total: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct DataV2 {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub seller_fee_basis_points: u16,
+    pub creators: Option<Vec<Creator>>,
+    pub collection: Option<Collection>,
+    pub uses: Option<Uses>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+struct CreateMetadataAccountArgsV3 {
+    data: DataV2,
+    is_mutable: bool,
+    collection_details: Option<u8>,
+}
+
+pub fn create_metadata_accounts_v3(
+    metadata: Pubkey,
+    mint: Pubkey,
+    mint_authority: Pubkey,
+    payer: Pubkey,
+    update_authority: Pubkey,
+    data: DataV2,
+) -> Instruction {
+    let args = CreateMetadataAccountArgsV3 {
+        data,
+        is_mutable: true,
+        collection_details: None,
+    };
+    let mut ix_data = vec![CREATE_METADATA_ACCOUNT_V3];
+    ix_data.extend(args.try_to_vec().unwrap());
+
+    Instruction {
+        program_id: ID,
+        accounts: vec![
+            AccountMeta::new(metadata, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(mint_authority, true),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(update_authority, true),
+            AccountMeta::new_readonly(anchor_lang::solana_program::system_program::ID, false),
+        ],
+        data: ix_data,
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+struct UpdateMetadataAccountArgsV2 {
+    data: Option<DataV2>,
+    update_authority: Option<Pubkey>,
+    primary_sale_happened: Option<bool>,
+    is_mutable: Option<bool>,
+}
+
+pub fn update_metadata_accounts_v2(
+    metadata: Pubkey,
+    update_authority: Pubkey,
+    data: DataV2,
+) -> Instruction {
+    let args = UpdateMetadataAccountArgsV2 {
+        data: Some(data),
+        update_authority: None,
+        primary_sale_happened: None,
+        is_mutable: None,
+    };
+    let mut ix_data = vec![UPDATE_METADATA_ACCOUNT_V2];
+    ix_data.extend(args.try_to_vec().unwrap());
+
+    Instruction {
+        program_id: ID,
+        accounts: vec![
+            AccountMeta::new(metadata, false),
+            AccountMeta::new_readonly(update_authority, true),
+        ],
+        data: ix_data,
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Data {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub seller_fee_basis_points: u16,
+    pub creators: Option<Vec<Creator>>,
+}
+
+/// Leading fields of a metadata account, up to and including the collection.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Metadata {
+    pub key: u8,
+    pub update_authority: Pubkey,
+    pub mint: Pubkey,
+    pub data: Data,
+    pub primary_sale_happened: bool,
+    pub is_mutable: bool,
+    pub edition_nonce: Option<u8>,
+    pub token_standard: Option<u8>,
+    pub collection: Option<Collection>,
+}
+
+impl Metadata {
+    pub fn from_account_info(info: &AccountInfo) -> std::result::Result<Self, ProgramError> {
+        if info.owner != &ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let data = info.try_borrow_data()?;
+        //accounts are allocated at max size, so trailing fields are ignored
+        Metadata::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Whether the metadata belongs to `mint` and sits in the verified `collection`.
+    pub fn is_verified_member(&self, mint: &Pubkey, collection: &Pubkey) -> bool {
+        if self.mint != *mint {
+            return false;
+        }
+        match &self.collection {
+            Some(c) => c.verified && c.key == *collection,
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! Example program holding a spl-staking position on behalf of its vaults.
+//!
+//! Each vault owns a staking `User` through its vault signer PDA and stakes,
+//! unstakes and claims through CPI.
+
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use spl_staking::program::SplStaking;
+use spl_staking::{Pool, User};
+
+declare_id!("Ak3kWZ9LJNgwHbbkNcqTQK2gvDjEB8k9NqTJsNStmCqS");
+
+#[program]
+pub mod stake_vault {
+    use super::*;
+
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        nonce: u8,
+        user_nonce: u8,
+        proof: Vec<[u8; 32]>,
+        max_stake: u64,
+    ) -> ProgramResult {
+        let vault = &mut ctx.accounts.vault;
+        vault.authority = ctx.accounts.authority.key();
+        vault.pool = ctx.accounts.pool.key();
+        vault.token_account = ctx.accounts.token_account.key();
+        vault.nonce = nonce;
+
+        let seeds = &[
+            ctx.accounts.vault.to_account_info().key.as_ref(),
+            &[nonce],
+        ];
+        let vault_signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.staking_program.to_account_info(),
+            spl_staking::cpi::accounts::CreateUser {
+                pool: ctx.accounts.pool.to_account_info(),
+                user: ctx.accounts.user.to_account_info(),
+                owner: ctx.accounts.vault_signer.to_account_info(),
+                payer: ctx.accounts.payer.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            vault_signer,
+        );
+        //allowlisted pools must list the vault signer
+        spl_staking::cpi::create_user(cpi_ctx, user_nonce, proof, max_stake)
+    }
+
+    pub fn stake(ctx: Context<StakeVault>, amount: u64) -> ProgramResult {
+        let seeds = &[
+            ctx.accounts.vault.to_account_info().key.as_ref(),
+            &[ctx.accounts.vault.nonce],
+        ];
+        let vault_signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.staking_program.to_account_info(),
+            ctx.accounts.stake_accounts(),
+            vault_signer,
+        );
+        spl_staking::cpi::stake(cpi_ctx, amount)
+    }
+
+    pub fn unstake<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, StakeVault<'info>>, amount: u64) -> ProgramResult {
+        let seeds = &[
+            ctx.accounts.vault.to_account_info().key.as_ref(),
+            &[ctx.accounts.vault.nonce],
+        ];
+        let vault_signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.staking_program.to_account_info(),
+            ctx.accounts.stake_accounts(),
+            vault_signer,
+        )
+        //the governance realm and token owner record, for pools with a realm
+        .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+        spl_staking::cpi::unstake(cpi_ctx, amount)
+    }
+
+    pub fn claim<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, ClaimVault<'info>>) -> ProgramResult {
+        let seeds = &[
+            ctx.accounts.vault.to_account_info().key.as_ref(),
+            &[ctx.accounts.vault.nonce],
+        ];
+        let vault_signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.staking_program.to_account_info(),
+            spl_staking::cpi::accounts::ClaimReward {
+                pool: ctx.accounts.pool.to_account_info(),
+                staking_vault: ctx.accounts.staking_vault.to_account_info(),
+                reward_a_vault: ctx.accounts.reward_a_vault.to_account_info(),
+                user: ctx.accounts.user.to_account_info(),
+                owner: ctx.accounts.vault_signer.to_account_info(),
+                staking_mint: ctx.accounts.staking_mint.to_account_info(),
+                reward_a_mint: ctx.accounts.reward_a_mint.to_account_info(),
+                reward_a_account: ctx.accounts.reward_a_account.to_account_info(),
+                reward_b_account: ctx.accounts.reward_b_account.to_account_info(),
+                pool_signer: ctx.accounts.pool_signer.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                associated_token_program: ctx.accounts.associated_token_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
+            },
+            vault_signer,
+        )
+        //extra reward vaults and destinations are passed through untouched
+        .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+        spl_staking::cpi::claim(cpi_ctx)
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u8)]
+pub struct Initialize<'info> {
+    #[account(zero)]
+    vault: Account<'info, Vault>,
+    authority: Signer<'info>,
+    #[account(
+        constraint = token_account.owner == vault_signer.key(),
+    )]
+    token_account: Account<'info, TokenAccount>,
+
+    // Staking accounts, checked by the staking program.
+    #[account(mut)]
+    pool: AccountLoader<'info, Pool>,
+    #[account(mut)]
+    user: UncheckedAccount<'info>,
+    #[account(mut)]
+    payer: Signer<'info>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            vault.to_account_info().key.as_ref()
+        ],
+        bump = nonce,
+    )]
+    vault_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    staking_program: Program<'info, SplStaking>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StakeVault<'info> {
+    #[account(
+        has_one = authority,
+        has_one = pool,
+        has_one = token_account,
+    )]
+    vault: Account<'info, Vault>,
+    authority: Signer<'info>,
+    #[account(mut)]
+    token_account: Account<'info, TokenAccount>,
+
+    // Staking accounts, checked by the staking program.
+    #[account(mut)]
+    pool: AccountLoader<'info, Pool>,
+    #[account(mut)]
+    staking_vault: UncheckedAccount<'info>,
+    #[account(mut)]
+    user: AccountLoader<'info, User>,
+    pool_signer: UncheckedAccount<'info>,
+
+    // Program signers.
+    #[account(
+        seeds = [
+            vault.to_account_info().key.as_ref()
+        ],
+        bump = vault.nonce,
+    )]
+    vault_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    staking_program: Program<'info, SplStaking>,
+    token_program: Program<'info, Token>,
+}
+
+impl<'info> StakeVault<'info> {
+    fn stake_accounts(&self) -> spl_staking::cpi::accounts::Stake<'info> {
+        spl_staking::cpi::accounts::Stake {
+            pool: self.pool.to_account_info(),
+            staking_vault: self.staking_vault.to_account_info(),
+            user: self.user.to_account_info(),
+            owner: self.vault_signer.to_account_info(),
+            stake_from_account: self.token_account.to_account_info(),
+            pool_signer: self.pool_signer.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct ClaimVault<'info> {
+    #[account(
+        has_one = authority,
+        has_one = pool,
+    )]
+    vault: Account<'info, Vault>,
+    authority: Signer<'info>,
+
+    // Staking accounts, checked by the staking program.
+    #[account(mut)]
+    pool: AccountLoader<'info, Pool>,
+    #[account(mut)]
+    staking_vault: UncheckedAccount<'info>,
+    #[account(mut)]
+    reward_a_vault: UncheckedAccount<'info>,
+    #[account(mut)]
+    user: AccountLoader<'info, User>,
+    staking_mint: Account<'info, Mint>,
+    reward_a_mint: Account<'info, Mint>,
+    // The vault signer's associated token accounts, created by the client.
+    #[account(mut)]
+    reward_a_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    reward_b_account: Account<'info, TokenAccount>,
+    pool_signer: UncheckedAccount<'info>,
+
+    // Program signers.
+    #[account(
+        mut,
+        seeds = [
+            vault.to_account_info().key.as_ref()
+        ],
+        bump = vault.nonce,
+    )]
+    vault_signer: UncheckedAccount<'info>,
+
+    // Misc.
+    staking_program: Program<'info, SplStaking>,
+    token_program: Program<'info, Token>,
+    associated_token_program: Program<'info, AssociatedToken>,
+    system_program: Program<'info, System>,
+    rent: Sysvar<'info, Rent>,
+}
+
+#[account]
+#[derive(Default)]
+pub struct Vault {
+    /// Signs stake, unstake and claim.
+    pub authority: Pubkey,
+    /// Pool the vault stakes into.
+    pub pool: Pubkey,
+    /// Token account of the vault signer staked from and unstaked to.
+    pub token_account: Pubkey,
+    /// Bump of the vault signer.
+    pub nonce: u8,
+}
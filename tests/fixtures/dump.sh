@@ -0,0 +1,13 @@
+#!/usr/bin/env sh
+# Dumps the mainnet programs the tests load into the local validator.
+# The binaries are not checked in, `yarn test` and `yarn anchor-test` fetch
+# whichever are missing before the validator starts.
+set -e
+cd "$(dirname "$0")"
+dump() {
+    if [ ! -f "$2" ]; then
+        solana program dump -u m "$1" "$2"
+    fi
+}
+dump metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s mpl_token_metadata.so
+dump GovER5Lthms3bLBqWub97yVrMmEogzX7xNjdXpPPCVZw spl_governance.so
@@ -6,6 +6,12 @@ import { SplStaking } from '../target/types/spl_staking';
 import * as utils from "./utils";
 import { User, claimForUsers } from "./user";
 import * as fs from 'fs';
+import {
+  withCreateRealm, withCreateTokenOwnerRecord, withCreateGovernance, withCreateProposal,
+  withSignOffProposal, withCastVote, withRelinquishVote, getProposal,
+  GovernanceConfig, VoteThreshold, VoteThresholdType, VoteTipping, VoteType, Vote, YesNoVote,
+  MintMaxVoteWeightSource, GoverningTokenConfigAccountArgs, GoverningTokenType,
+} from "@solana/spl-governance";
 
 describe('spl-staking', () => {
 
@@ -75,7 +81,991 @@ describe('spl-staking', () => {
     await user.unstakeTokens(100_000);
     await user.closeUser();
     await funders[0].pausePool();
+
+    //a stray deposit cannot hold the pool open, it is swept to the authority
+    let poolObject = await program.account.pool.fetch(pool);
+    await user.stakingMintObject.transfer(user.stakingPubkey, poolObject.stakingVault, user.keypair, [], 1_000);
     await funders[0].closePool();
+    let swept = await provider.connection.getTokenAccountBalance(funders[0].stakingPubkey);
+    assert.equal(swept.value.amount, "1000");
+  });
+
+  it('Partner stakes on behalf of a user', async () => {
+    let partnerPool = anchor.web3.Keypair.generate();
+    await funders[0].initializePool(partnerPool);
+
+    let partner = users[2];
+    let beneficiary = users[1];
+    partner.poolPubkey = partnerPool.publicKey;
+    //the beneficiary need not be present for the deposit that creates their user
+    await partner.stakeTokensFor(beneficiary.pubkey, 50_000);
+
+    const [beneficiaryUserPubkey] = await anchor.web3.PublicKey.findProgramAddress(
+      [beneficiary.pubkey.toBuffer(), partnerPool.publicKey.toBuffer()],
+      program.programId
+    );
+    let userObject = await program.account.user.fetch(beneficiaryUserPubkey);
+    assert.ok(userObject.owner.equals(beneficiary.pubkey));
+    assert.equal(userObject.balanceStaked.toNumber(), 50_000);
+
+    //without the beneficiary's signature deposits leave the lock and cooldown alone
+    await funders[0].setStakeForResetsLock(true);
+    await wait(2);
+    await partner.stakeTokensFor(beneficiary.pubkey, 49_999);
+    let toppedUp = await program.account.user.fetch(beneficiaryUserPubkey);
+    assert.equal(toppedUp.balanceStaked.toNumber(), 99_999);
+    assert.ok(toppedUp.stakeTime.eq(userObject.stakeTime));
+    await partner.stakeTokensFor(beneficiary.pubkey, 1, beneficiary.keypair);
+    toppedUp = await program.account.user.fetch(beneficiaryUserPubkey);
+    assert.ok(toppedUp.stakeTime.gt(userObject.stakeTime));
+  });
+
+  it('User transfers part of a position', async () => {
+    let pool = funders[0].poolPubkey;
+    let sender = users[1];
+    let recipient = users[3];
+    sender.poolPubkey = pool;
+    await sender.transferPosition(recipient.pubkey, 40_000);
+
+    const [senderUserPubkey] = await anchor.web3.PublicKey.findProgramAddress(
+      [sender.pubkey.toBuffer(), pool.toBuffer()],
+      program.programId
+    );
+    const [recipientUserPubkey] = await anchor.web3.PublicKey.findProgramAddress(
+      [recipient.pubkey.toBuffer(), pool.toBuffer()],
+      program.programId
+    );
+    let senderObject = await program.account.user.fetch(senderUserPubkey);
+    let recipientObject = await program.account.user.fetch(recipientUserPubkey);
+    assert.equal(senderObject.balanceStaked.toNumber(), 60_000);
+    assert.equal(recipientObject.balanceStaked.toNumber(), 40_000);
+    assert.ok(recipientObject.stakeTime.eq(senderObject.stakeTime));
+
+    await funders[0].setTransfersEnabled(false);
+    await assert.rejects(sender.transferPosition(recipient.pubkey, 10_000));
+    await funders[0].setTransfersEnabled(true);
+  });
+
+  it('Funder starts a reward campaign', async () => {
+    const day = 24 * 60 * 60;
+    await assert.rejects(funders[0].notifyRewardAmount(1_000_000, day - 1));
+
+    await funders[0].notifyRewardAmount(8_640_000, day);
+    let poolObject = await program.account.pool.fetch(funders[0].poolPubkey);
+    assert.equal(poolObject.rewardARate.toNumber(), 100);
+    assert.equal(poolObject.periodFinish.sub(poolObject.lastUpdateTime).toNumber(), day);
+    assert.equal(poolObject.totalStaked.toNumber(), 100_000);
+  });
+
+  it('User stakes into a position nft', async () => {
+    let user = users[4];
+    user.poolPubkey = funders[0].poolPubkey;
+    let [positionMint, positionPubkey, positionTokenAccount] = await user.stakePosition(50_000);
+
+    let nftBalance = await provider.connection.getTokenAccountBalance(positionTokenAccount);
+    assert.equal(nftBalance.value.amount, "1");
+    let positionObject = await program.account.user.fetch(positionPubkey);
+    assert.ok(positionObject.owner.equals(positionMint));
+    assert.equal(positionObject.balanceStaked.toNumber(), 50_000);
+
+    await user.claimPosition(positionMint);
+  });
+
+  it('Creates an nft pool for a collection', async () => {
+    let nftPoolKeypair = anchor.web3.Keypair.generate();
+    let nftFunder = new User(10);
+    await nftFunder.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 0, mintA.publicKey, 100_000_000_000);
+
+    let collection = anchor.web3.Keypair.generate().publicKey;
+    await nftFunder.initializeNftPool(nftPoolKeypair, collection, 1_000);
+    await nftFunder.fund(1_000_000_000);
+
+    let poolObject = await program.account.pool.fetch(nftPoolKeypair.publicKey);
+    assert.equal(poolObject.kind, 1);
+    assert.ok(poolObject.nftCollection.equals(collection));
+    assert.equal(poolObject.nftRewardRate.toNumber(), 1_000);
+    assert.equal(poolObject.transfersEnabled, 0);
+  });
+
+  it('Stakes collection nfts for reward a and closes the nft pool', async () => {
+    let nftPoolKeypair = anchor.web3.Keypair.generate();
+    let nftFunder = new User(11);
+    let collector = new User(12);
+    await nftFunder.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 0, mintA.publicKey, 100_000_000_000);
+    await collector.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 0, mintA.publicKey, 0);
+
+    let collection = await utils.createNft(provider, provider.wallet.publicKey, null, true);
+    let otherCollection = await utils.createNft(provider, provider.wallet.publicKey, null, true);
+    let member = await utils.createNft(provider, collector.pubkey, collection);
+    let outsider = await utils.createNft(provider, collector.pubkey, otherCollection);
+
+    await nftFunder.initializeNftPool(nftPoolKeypair, collection, 1_000);
+    await nftFunder.fund(1_000_000_000);
+    await collector.createUserStakingAccount(nftPoolKeypair.publicKey);
+
+    await utils.rejectsWith(collector.stakeNft(outsider), "Nft is not a verified member of the pool collection.");
+    let [stakedNft, nftAccount] = await collector.stakeNft(member);
+    let stakedObject = await program.account.stakedNft.fetch(stakedNft);
+    assert.ok(stakedObject.owner.equals(collector.pubkey));
+    let userObject = await program.account.user.fetch(collector.userPubkey);
+    assert.equal(userObject.balanceStaked.toNumber(), 1);
+
+    await wait(2);
+    let amtA = await collector.claimNftRewards();
+    assert.ok(amtA > 0);
+
+    await collector.unstakeNft(member);
+    let nftBalance = await provider.connection.getTokenAccountBalance(nftAccount);
+    assert.equal(nftBalance.value.amount, "1");
+    assert.equal(await provider.connection.getAccountInfo(stakedNft), null);
+    await collector.claimNftRewards();
+    await collector.closeUser();
+
+    //nft pools hold no staking vault, they close through their own path
+    await nftFunder.pausePool();
+    await assert.rejects(nftFunder.closePool());
+    await nftFunder.closeNftPool();
+    assert.equal(await provider.connection.getAccountInfo(nftPoolKeypair.publicKey), null);
+  });
+
+  it('Partner co-incentivizes a pool with its own token', async () => {
+    const day = 24 * 60 * 60;
+    let partnerMint = await utils.createMint(provider, 6);
+    let rewardVault = await funders[0].addReward(partnerMint);
+    await assert.rejects(funders[0].addReward(partnerMint));
+
+    let funderPartnerAccount = await partnerMint.createAssociatedTokenAccount(funders[0].pubkey);
+    await partnerMint.mintTo(funderPartnerAccount, provider.wallet.payer, [], 8_640_000);
+    await funders[0].notifyExtraRewardAmount(0, rewardVault, funderPartnerAccount, 8_640_000, day);
+
+    let poolObject = await program.account.pool.fetch(funders[0].poolPubkey);
+    assert.ok(poolObject.extraRewards[0].mint.equals(partnerMint.publicKey));
+    assert.equal(poolObject.extraRewards[0].rewardRate.toNumber(), 100);
+  });
+
+  it('Creates pools through the registry', async () => {
+    let factoryAdmin = new User(20);
+    await factoryAdmin.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 0, mintA.publicKey, 0);
+
+    //only the wallet that deployed the program can hand out the registry
+    await utils.rejectsWith(factoryAdmin.initializeRegistry(), "Signer is not the program upgrade authority.");
+    let registryPubkey = await factoryAdmin.initializeRegistry(provider.wallet.payer);
+    let firstPool = await factoryAdmin.createPool(0);
+    let secondPool = await factoryAdmin.createPool(1);
+    await assert.rejects(factoryAdmin.createPool(1));
+
+    let registryObject = await program.account.poolRegistry.fetch(registryPubkey);
+    assert.equal(registryObject.pools.length, 2);
+    assert.ok(registryObject.pools[0].equals(firstPool));
+    assert.ok(registryObject.pools[1].equals(secondPool));
+
+    let poolObject = await program.account.pool.fetch(firstPool);
+    assert.ok(poolObject.stakingMint.equals(stakingMint.publicKey));
+  });
+
+  it('Migrates pool and user accounts to the current layout', async () => {
+    let migrator = new User(30);
+    await migrator.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 0, mintA.publicKey, 0);
+    await migrator.createUserStakingAccount(funders[0].poolPubkey);
+
+    await migrator.migratePool();
+    await migrator.migrateUser();
+    //migrating an up to date account is a no-op
+    await migrator.migrateUser();
+
+    let poolObject = await program.account.pool.fetch(migrator.poolPubkey);
+    assert.equal(poolObject.version, 7);
+    //settings of an up to date pool are left alone
+    assert.equal(poolObject.transfersEnabled, 1);
+    assert.equal(poolObject.slashIndex.toString(), "1000000000000000000");
+    let userObject = await program.account.user.fetch(migrator.userPubkey);
+    assert.equal(userObject.version, 5);
+  });
+
+  it('Loads accounts written before the zero-copy layout', async () => {
+    //see tests/fixtures, the pool and user were encoded by the first release
+    let owner = anchor.web3.Keypair.fromSecretKey(
+      Uint8Array.from(JSON.parse(fs.readFileSync("tests/fixtures/legacy_owner.json", "utf8")))
+    );
+    let legacyStakingMint = new anchor.web3.PublicKey("Fnop6tLANymw93MVfGqewtNGyyPVrCGCcQNVG9vrNqUD");
+    let legacyRewardAMint = new anchor.web3.PublicKey("4x4Cngb9Q91MdWFJ5zC6nEUeGkXKjrUo3KgnnWSbEG97");
+    let migrator = new User(31);
+    await migrator.init(anchor.web3.LAMPORTS_PER_SOL, legacyStakingMint, 0, legacyRewardAMint, 0, owner);
+    migrator.poolPubkey = new anchor.web3.PublicKey("EE75oDzHq1KKM1zW3oioHBimmE3t6G6Swu1odsVif5WE");
+    migrator.userPubkey = new anchor.web3.PublicKey("ECDVeKWxGaq32YUPjL3UK85RFciFfZxaafen8qYvzFku");
+    await migrator.migratePool();
+    await migrator.migrateUser();
+
+    let poolObject = await program.account.pool.fetch(migrator.poolPubkey);
+    assert.equal(poolObject.version, 7);
+    assert.ok(poolObject.authority.equals(owner.publicKey));
+    assert.ok(poolObject.stakingMint.equals(legacyStakingMint));
+    assert.equal(poolObject.nonce, 255);
+    assert.equal(poolObject.paused, 1);
+    assert.equal(poolObject.userStakeCount, 1);
+    assert.ok(poolObject.funders[0].equals(poolObject.authority));
+    //every field added since gets the default init_pool writes
+    assert.equal(poolObject.kind, 0);
+    assert.equal(poolObject.transfersEnabled, 1);
+    assert.equal(poolObject.stakeForResetsLock, 0);
+    assert.equal(poolObject.slashIndex.toString(), "1000000000000000000");
+    assert.equal(poolObject.unstakeLockPeriod.toNumber(), 0);
+    assert.equal(poolObject.maxTotalStaked.toNumber(), 0);
+    assert.ok(poolObject.boostPool.equals(anchor.web3.PublicKey.default));
+    assert.ok(poolObject.governanceRealm.equals(anchor.web3.PublicKey.default));
+    //the legacy stake is counted once its user migrates
+    assert.equal(poolObject.totalStaked.toNumber(), 1_000_000);
+    assert.equal(poolObject.workingSupply.toNumber(), 1_000_000);
+
+    let userObject = await program.account.user.fetch(migrator.userPubkey);
+    assert.equal(userObject.version, 5);
+    assert.ok(userObject.pool.equals(migrator.poolPubkey));
+    assert.ok(userObject.owner.equals(owner.publicKey));
+    assert.equal(userObject.balanceStaked.toNumber(), 1_000_000);
+    assert.equal(userObject.stakeTime.toNumber(), 1_600_000_000);
+    assert.equal(userObject.nonce, 252);
+    //backfilled from the legacy fields
+    assert.equal(userObject.workingBalance.toNumber(), 1_000_000);
+    assert.equal(userObject.loyaltyStart.toNumber(), 1_600_000_000);
+    assert.equal(userObject.tier, 0);
+    assert.equal(userObject.slashIndex.toString(), "1000000000000000000");
+
+    //migrating twice counts nothing twice
+    await migrator.migrateUser();
+    poolObject = await program.account.pool.fetch(migrator.poolPubkey);
+    assert.equal(poolObject.totalStaked.toNumber(), 1_000_000);
+
+    //the legacy staker claims and gets their stake back
+    await migrator.unpausePool();
+    await migrator.claim();
+    await migrator.unstakeTokens(1_000_000);
+    let unstaked = await provider.connection.getTokenAccountBalance(migrator.stakingPubkey);
+    assert.equal(unstaked.value.amount, "1000000");
+    poolObject = await program.account.pool.fetch(migrator.poolPubkey);
+    assert.equal(poolObject.totalStaked.toNumber(), 0);
+    assert.equal(poolObject.workingSupply.toNumber(), 0);
+  });
+
+  it('Tracks pool statistics', async () => {
+    let statsPool = anchor.web3.Keypair.generate();
+    await funders[0].initializePool(statsPool);
+    await funders[0].fund(1_000_000);
+
+    let staker = new User(40);
+    await staker.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 100_000, mintA.publicKey, 0);
+    await staker.createUserStakingAccount(statsPool.publicKey);
+    await staker.stakeTokens(100_000);
+
+    let poolObject = await program.account.pool.fetch(statsPool.publicKey);
+    assert.equal(poolObject.totalStaked.toNumber(), 100_000);
+    assert.equal(poolObject.totalFundedA.toNumber(), 1_000_000);
+    assert.equal(poolObject.totalRewardsAPaid.toNumber(), 0);
+    assert.ok(poolObject.lastActivityTime.toNumber() > 0);
+  });
+
+  it('Reads the unlock time through a view instruction', async () => {
+    let staker = new User(41);
+    await staker.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 100_000, mintA.publicKey, 0);
+    await staker.createUserStakingAccount(funders[0].poolPubkey);
+    await staker.stakeTokens(100_000);
+
+    let userObject = await program.account.user.fetch(staker.userPubkey);
+    let unlock = await staker.getUnlockTime();
+    assert.ok(unlock.stakeTime.eq(userObject.stakeTime));
+    assert.equal(unlock.unlockTime.toNumber(), userObject.stakeTime.toNumber() + 30 * 24 * 60 * 60);
+    assert.equal(unlock.unlocked, false);
+  });
+
+  it('Reports pending rewards and pool summaries that a claim then pays', async () => {
+    const day = 24 * 60 * 60;
+    let viewPool = anchor.web3.Keypair.generate();
+    await funders[0].initializePool(viewPool);
+    let viewer = new User(32);
+    await viewer.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 100_000, mintA.publicKey, 0);
+    await viewer.createUserStakingAccount(viewPool.publicKey);
+    //small enough to earn no reward b, so only reward a accrues
+    await viewer.stakeTokens(100_000);
+    await funders[0].notifyRewardAmount(8_640_000, day);
+    await wait(3);
+
+    let slot = await provider.connection.getSlot();
+    let viewTimeLowerBound = await provider.connection.getBlockTime(slot);
+    let pending = await viewer.getPendingRewards();
+    let summary = await viewer.getPoolSummary();
+    assert.ok(pending.rewardA.toNumber() > 0);
+    assert.equal(pending.rewardB.toNumber(), 0);
+    assert.ok(pending.extraRewards.every(x => x.isZero()));
+
+    let balanceBefore = await provider.connection.getTokenAccountBalance(viewer.mintAPubkey);
+    await viewer.claim();
+    let balanceAfter = await provider.connection.getTokenAccountBalance(viewer.mintAPubkey);
+    let claimed = Number(balanceAfter.value.amount) - Number(balanceBefore.value.amount);
+
+    //the claim lands a few seconds after the view, the sole staker earns the full rate meanwhile
+    let userObject = await program.account.user.fetch(viewer.userPubkey);
+    let elapsed = userObject.lastUpdateTime.toNumber() - viewTimeLowerBound;
+    assert.ok(claimed >= pending.rewardA.toNumber());
+    assert.ok(claimed <= pending.rewardA.toNumber() + 100 * elapsed);
+
+    let poolObject = await program.account.pool.fetch(viewPool.publicKey);
+    assert.equal(summary.paused, false);
+    assert.equal(summary.userStakeCount, poolObject.userStakeCount);
+    assert.equal(summary.totalStaked.toNumber(), poolObject.totalStaked.toNumber());
+    assert.equal(summary.rewardARate.toNumber(), 100);
+    assert.equal(summary.periodFinish.toNumber(), poolObject.periodFinish.toNumber());
+    assert.equal(summary.totalFundedA.toNumber(), poolObject.totalFundedA.toNumber());
+    assert.ok(summary.rewardAPerTokenStored.lte(poolObject.rewardAPerTokenStored));
+    assert.equal(poolObject.totalRewardsAPaid.sub(summary.totalRewardsAPaid).toNumber(), claimed);
+  });
+
+  it('Enforces deposit caps', async () => {
+    let cappedPool = anchor.web3.Keypair.generate();
+    await funders[0].initializePool(cappedPool);
+    await funders[0].setDepositCaps(150_000, 100_000);
+
+    let staker = new User(42);
+    await staker.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 200_000, mintA.publicKey, 0);
+    await staker.createUserStakingAccount(cappedPool.publicKey);
+    await assert.rejects(staker.stakeTokens(100_001));
+    await staker.stakeTokens(80_000);
+
+    let capacity = await staker.getRemainingCapacity();
+    assert.equal(capacity.maxTotalStaked.toNumber(), 150_000);
+    assert.equal(capacity.maxPerUser.toNumber(), 100_000);
+    assert.equal(capacity.remaining.toNumber(), 20_000);
+
+    let other = new User(43);
+    await other.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 100_000, mintA.publicKey, 0);
+    await other.createUserStakingAccount(cappedPool.publicKey);
+    await assert.rejects(other.stakeTokens(70_001));
+    await other.stakeTokens(70_000);
+
+    //a transfer cannot push the recipient past its cap either
+    await utils.rejectsWith(staker.transferPosition(other.pubkey, 30_001), "Stake would exceed the per user deposit cap.");
+    await staker.transferPosition(other.pubkey, 30_000);
+    let otherObject = await program.account.user.fetch(other.userPubkey);
+    assert.equal(otherObject.balanceStaked.toNumber(), 100_000);
+  });
+
+  it('Restricts an allowlisted pool to proven wallets', async () => {
+    let privatePool = anchor.web3.Keypair.generate();
+    await funders[0].initializePool(privatePool);
+
+    let listed = [44, 45, 46].map(a => new User(a));
+    await Promise.all(
+      listed.map(a => a.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 100_000, mintA.publicKey, 0))
+    );
+    let caps = [0, 50_000, 0];
+    let tree = utils.allowlistTree(listed.map((a, i) => utils.allowlistLeaf(a.pubkey, caps[i])));
+    await funders[0].setAllowlistRoot(tree.root);
+
+    //a wrong cap or someone else's proof does not verify
+    await assert.rejects(listed[1].createUserStakingAccount(privatePool.publicKey, tree.proofs[1], 0));
+    await assert.rejects(listed[2].createUserStakingAccount(privatePool.publicKey, tree.proofs[0], 0));
+
+    await listed[0].createUserStakingAccount(privatePool.publicKey, tree.proofs[0], 0);
+    await assert.rejects(listed[0].stakeTokensFor(users[0].pubkey, 10_000));
+    await listed[1].createUserStakingAccount(privatePool.publicKey, tree.proofs[1], 50_000);
+    await assert.rejects(listed[1].stakeTokens(50_001));
+    await listed[1].stakeTokens(50_000);
+
+    //rotating the root keeps existing stakers in
+    await funders[0].setAllowlistRoot(utils.allowlistLeaf(listed[0].pubkey, 0));
+    await listed[0].stakeTokens(100_000);
+    await assert.rejects(listed[2].createUserStakingAccount(privatePool.publicKey, tree.proofs[2], 0));
+  });
+
+  it('Locks tokens for decaying voting power', async () => {
+    const year = 365 * 24 * 60 * 60;
+    let locker = new User(47);
+    await locker.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 300_000, mintA.publicKey, 0);
+    locker.poolPubkey = funders[0].poolPubkey;
+
+    let now = Math.floor(Date.now() / 1000);
+    await assert.rejects(locker.createLock(100_000, now + 5 * year));
+    await locker.createLock(100_000, now + 2 * year);
+    await assert.rejects(locker.extendLock(now + year));
+    await locker.extendLock(now + 4 * year);
+    await locker.increaseLockAmount(100_000);
+
+    let power = await locker.getVotingPower(now + 2 * year);
+    assert.equal(power.amount.toNumber(), 200_000);
+    assert.equal(power.votingPower.toNumber(), 100_000);
+    power = await locker.getVotingPower(now + 4 * year);
+    assert.equal(power.votingPower.toNumber(), 0);
+    await assert.rejects(locker.withdrawLock());
+
+    let poolObject = await program.account.pool.fetch(locker.poolPubkey);
+    assert.ok(poolObject.totalLocked.toNumber() >= 200_000);
+  });
+
+  it('Keeps a pool with open locks from closing', async () => {
+    const year = 365 * 24 * 60 * 60;
+    let lockPool = anchor.web3.Keypair.generate();
+    await funders[0].initializePool(lockPool);
+    let locker = new User(44);
+    await locker.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 100_000, mintA.publicKey, 0);
+    locker.poolPubkey = lockPool.publicKey;
+    await locker.createLock(100_000, Math.floor(Date.now() / 1000) + year);
+
+    //the escrowed tokens would be stranded behind a closed pool
+    await funders[0].pausePool();
+    await assert.rejects(funders[0].closePool());
+    let poolObject = await program.account.pool.fetch(lockPool.publicKey);
+    assert.equal(poolObject.totalLocked.toNumber(), 100_000);
+  });
+
+  it('Writes governance voter weight records', async () => {
+    let governancePool = anchor.web3.Keypair.generate();
+    await funders[0].initializePool(governancePool);
+    let voter = new User(48);
+    await voter.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 100_000, mintA.publicKey, 0);
+    await voter.createUserStakingAccount(governancePool.publicKey);
+    await voter.stakeTokens(100_000);
+    await assert.rejects(voter.updateVoterWeightRecord());
+
+    let realm = anchor.web3.Keypair.generate().publicKey;
+    await funders[0].setGovernanceRealm(realm);
+    let record = await voter.updateVoterWeightRecord();
+    let maxRecord = await voter.updateMaxVoterWeightRecord();
+
+    //decode by hand, governance reads the raw addin layout rather than our idl
+    let data = (await provider.connection.getAccountInfo(record)).data;
+    assert.ok(new anchor.web3.PublicKey(data.slice(8, 40)).equals(realm));
+    assert.ok(new anchor.web3.PublicKey(data.slice(40, 72)).equals(stakingMint.publicKey));
+    assert.ok(new anchor.web3.PublicKey(data.slice(72, 104)).equals(voter.pubkey));
+    assert.equal(new anchor.BN(data.slice(104, 112), "le").toNumber(), 100_000);
+    assert.equal(data[112], 1);
+
+    let maxData = (await provider.connection.getAccountInfo(maxRecord)).data;
+    assert.ok(new anchor.web3.PublicKey(maxData.slice(8, 40)).equals(realm));
+    assert.equal(new anchor.BN(maxData.slice(72, 80), "le").toNumber(), 100_000);
+  });
+
+  it('Locks stake that is counted in a governance vote', async () => {
+    //loaded into the local validator from tests/fixtures, see Anchor.toml
+    const governanceProgram = new anchor.web3.PublicKey("GovER5Lthms3bLBqWub97yVrMmEogzX7xNjdXpPPCVZw");
+    const version = 3;
+    let governancePool = anchor.web3.Keypair.generate();
+    await funders[0].initializePool(governancePool);
+    let voter = new User(45);
+    let other = new User(46);
+    await voter.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 150_000, mintA.publicKey, 0);
+    await other.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 0, mintA.publicKey, 0);
+    await voter.createUserStakingAccount(governancePool.publicKey);
+    await voter.stakeTokens(100_000);
+    let [positionMint, positionPubkey] = await voter.stakePosition(50_000);
+
+    //a realm over the staking mint that takes community weight from this program
+    let ixs = [];
+    let realm = await withCreateRealm(
+      ixs, governanceProgram, version, "Stakers " + governancePool.publicKey.toBase58().slice(0, 8),
+      voter.pubkey, stakingMint.publicKey, voter.pubkey, undefined,
+      MintMaxVoteWeightSource.FULL_SUPPLY_FRACTION, new anchor.BN(1),
+      new GoverningTokenConfigAccountArgs({
+        voterWeightAddin: program.programId,
+        maxVoterWeightAddin: undefined,
+        tokenType: GoverningTokenType.Liquid,
+      }),
+    );
+    let tokenOwnerRecord = await withCreateTokenOwnerRecord(
+      ixs, governanceProgram, version, realm, voter.pubkey, stakingMint.publicKey, voter.pubkey
+    );
+    await voter.provider.send(new anchor.web3.Transaction().add(...ixs));
+    await funders[0].setGovernanceRealm(realm);
+
+    //a position has no wallet to vote for, so it cannot write a voter weight record
+    const [positionRecord] = await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from("voter_weight_record"), governancePool.publicKey.toBuffer(), positionMint.toBuffer()],
+      program.programId
+    );
+    await utils.rejectsWith(voter.program.rpc.updateVoterWeightRecord({
+      accounts: {
+        pool: governancePool.publicKey,
+        user: positionPubkey,
+        voterWeightRecord: positionRecord,
+        payer: voter.pubkey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      },
+    }), "A seeds constraint was violated");
+
+    let [refresh, record] = await voter.updateVoterWeightRecordInstruction();
+    ixs = [refresh];
+    let governance = await withCreateGovernance(
+      ixs, governanceProgram, version, realm, anchor.web3.Keypair.generate().publicKey,
+      new GovernanceConfig({
+        communityVoteThreshold: new VoteThreshold({ type: VoteThresholdType.YesVotePercentage, value: 60 }),
+        minCommunityTokensToCreateProposal: new anchor.BN(1),
+        minInstructionHoldUpTime: 0,
+        baseVotingTime: 24 * 60 * 60,
+        communityVoteTipping: VoteTipping.Disabled,
+        councilVoteThreshold: new VoteThreshold({ type: VoteThresholdType.Disabled }),
+        councilVetoVoteThreshold: new VoteThreshold({ type: VoteThresholdType.Disabled }),
+        minCouncilTokensToCreateProposal: new anchor.BN(1),
+        councilVoteTipping: VoteTipping.Disabled,
+        communityVetoVoteThreshold: new VoteThreshold({ type: VoteThresholdType.Disabled }),
+        votingCoolOffTime: 0,
+        depositExemptProposalCount: 10,
+      }),
+      tokenOwnerRecord, voter.pubkey, voter.pubkey, record,
+    );
+    await voter.provider.send(new anchor.web3.Transaction().add(...ixs));
+
+    [refresh] = await voter.updateVoterWeightRecordInstruction();
+    ixs = [refresh];
+    let proposal = await withCreateProposal(
+      ixs, governanceProgram, version, realm, governance, tokenOwnerRecord, "Proposal", "",
+      stakingMint.publicKey, voter.pubkey, 0, VoteType.SINGLE_CHOICE, ["Approve"], true, voter.pubkey, record,
+    );
+    withSignOffProposal(ixs, governanceProgram, version, realm, governance, proposal, voter.pubkey, undefined, tokenOwnerRecord);
+    await voter.provider.send(new anchor.web3.Transaction().add(...ixs));
+
+    [refresh] = await voter.updateVoterWeightRecordInstruction();
+    ixs = [refresh];
+    let voteRecord = await withCastVote(
+      ixs, governanceProgram, version, realm, governance, proposal, tokenOwnerRecord, tokenOwnerRecord,
+      voter.pubkey, stakingMint.publicKey, Vote.fromYesNoVote(YesNoVote.Yes), voter.pubkey, record,
+    );
+    await voter.provider.send(new anchor.web3.Transaction().add(...ixs));
+
+    let proposalObject = await getProposal(provider.connection, proposal);
+    assert.equal(proposalObject.account.options[0].voteWeight.toNumber(), 100_000);
+    let recordData = (await provider.connection.getAccountInfo(tokenOwnerRecord)).data;
+    assert.equal(recordData.readUInt32LE(105), 1);
+
+    //the counted stake cannot move to another wallet to be counted again
+    let governanceAccounts = [realm, tokenOwnerRecord].map(pubkey => ({ pubkey, isSigner: false, isWritable: false }));
+    await utils.rejectsWith(voter.transferPosition(other.pubkey, 100_000), "Governance realm or token owner record does not match the pool.");
+    await utils.rejectsWith(voter.transferPosition(other.pubkey, 100_000, governanceAccounts), "Stake is counted in a governance vote, relinquish it first.");
+
+    //withdrawing the vote releases the stake
+    ixs = [];
+    await withRelinquishVote(
+      ixs, governanceProgram, version, realm, governance, proposal, tokenOwnerRecord,
+      stakingMint.publicKey, voteRecord, voter.pubkey, voter.pubkey,
+    );
+    await voter.provider.send(new anchor.web3.Transaction().add(...ixs));
+    await voter.transferPosition(other.pubkey, 100_000, governanceAccounts);
+    const [otherUser] = await anchor.web3.PublicKey.findProgramAddress(
+      [other.pubkey.toBuffer(), governancePool.publicKey.toBuffer()],
+      program.programId
+    );
+    let otherObject = await program.account.user.fetch(otherUser);
+    assert.equal(otherObject.balanceStaked.toNumber(), 100_000);
+  });
+
+  it('Splits emissions across pools by gauge votes', async () => {
+    const year = 365 * 24 * 60 * 60;
+    let gaugeAdmin = new User(49);
+    await gaugeAdmin.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 100_000, mintA.publicKey, 1_500_000);
+    let escrowPool = anchor.web3.Keypair.generate();
+    let firstPool = anchor.web3.Keypair.generate();
+    let secondPool = anchor.web3.Keypair.generate();
+    await gaugeAdmin.initializePool(firstPool);
+    await gaugeAdmin.initializePool(secondPool);
+    await gaugeAdmin.initializePool(escrowPool);
+
+    //votes cast in epoch zero set the rates for epoch one
+    let controller = anchor.web3.Keypair.generate();
+    let emissionVault = await gaugeAdmin.createGaugeController(controller, escrowPool.publicKey, 1_000_000, 10);
+    await gaugeAdmin.fundGaugeController(controller.publicKey, 1_500_000);
+    let firstGauge = await gaugeAdmin.createGauge(controller.publicKey, firstPool.publicKey);
+    let secondGauge = await gaugeAdmin.createGauge(controller.publicKey, secondPool.publicKey);
+
+    await gaugeAdmin.createLock(100_000, Math.floor(Date.now() / 1000) + year);
+    await gaugeAdmin.voteGauge(controller.publicKey, firstGauge, 7_500);
+    await gaugeAdmin.voteGauge(controller.publicKey, secondGauge, 2_500);
+    await assert.rejects(gaugeAdmin.voteGauge(controller.publicKey, secondGauge, 2_501));
+
+    await wait(10);
+    await gaugeAdmin.checkpointGauges(controller.publicKey, [
+      [firstGauge, firstPool.publicKey],
+      [secondGauge, secondPool.publicKey],
+    ]);
+    //each pool's share moves out of the controller's vault and backs its rate
+    let firstObject = await program.account.pool.fetch(firstPool.publicKey);
+    let secondObject = await program.account.pool.fetch(secondPool.publicKey);
+    let firstVault = await provider.connection.getTokenAccountBalance(firstObject.rewardAVault);
+    let secondVault = await provider.connection.getTokenAccountBalance(secondObject.rewardAVault);
+    let remaining = await provider.connection.getTokenAccountBalance(emissionVault);
+    assert.equal(firstVault.value.amount, "750000");
+    assert.equal(secondVault.value.amount, "250000");
+    assert.equal(remaining.value.amount, "500000");
+    assert.equal(firstObject.totalFundedA.toNumber(), 750_000);
+    assert.ok(firstObject.rewardARate.toNumber() >= 75_000);
+    assert.ok(secondObject.rewardARate.toNumber() >= 25_000);
+    assert.ok(firstObject.rewardARate.toNumber() * (firstObject.periodFinish.toNumber() - firstObject.lastUpdateTime.toNumber()) <= 750_000);
+
+    //a second checkpoint in the same epoch pays nothing more
+    await gaugeAdmin.checkpointGauges(controller.publicKey, [[firstGauge, firstPool.publicKey]]);
+    remaining = await provider.connection.getTokenAccountBalance(emissionVault);
+    assert.equal(remaining.value.amount, "500000");
+  });
+
+  it('Boosts reward A for stakers of the boost pool', async () => {
+    let boostedPool = anchor.web3.Keypair.generate();
+    let boostPool = anchor.web3.Keypair.generate();
+    await funders[0].initializePool(boostPool);
+    await funders[0].initializePool(boostedPool);
+    await funders[0].setBoostPool(boostPool.publicKey);
+
+    let holder = new User(51);
+    let plain = new User(52);
+    await holder.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 200_000, mintA.publicKey, 0);
+    await plain.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 100_000, mintA.publicKey, 0);
+    await holder.createUserStakingAccount(boostPool.publicKey);
+    await holder.stakeTokens(100_000);
+    let holderBoostUser = holder.userPubkey;
+
+    await holder.createUserStakingAccount(boostedPool.publicKey);
+    await holder.stakeTokens(100_000);
+    await plain.createUserStakingAccount(boostedPool.publicKey);
+    await plain.stakeTokens(100_000);
+
+    //the holder owns the whole boost pool, a stranger pokes them to full boost
+    await plain.pokeBoost(boostedPool.publicKey, holder.userPubkey, holder.pubkey);
+    await plain.pokeBoost(boostedPool.publicKey, plain.userPubkey, plain.pubkey);
+
+    let holderObject = await program.account.user.fetch(holder.userPubkey);
+    let plainObject = await program.account.user.fetch(plain.userPubkey);
+    assert.equal(holderObject.workingBalance.toNumber(), 100_000);
+    assert.equal(plainObject.workingBalance.toNumber(), 40_000);
+    let poolObject = await program.account.pool.fetch(boostedPool.publicKey);
+    assert.equal(poolObject.workingSupply.toNumber(), 140_000);
+    let boostUserObject = await program.account.user.fetch(holderBoostUser);
+    assert.equal(boostUserObject.workingBalance.toNumber(), 100_000);
+  });
+
+  it('Grows reward A with continuous staking time', async () => {
+    let loyaltyPool = anchor.web3.Keypair.generate();
+    await funders[0].initializePool(loyaltyPool);
+    await funders[0].setLoyaltySchedule(4, 1_000, 2_000);
+
+    let loyal = new User(53);
+    let recipient = new User(54);
+    await loyal.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 200_000, mintA.publicKey, 0);
+    await recipient.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 0, mintA.publicKey, 0);
+    await loyal.createUserStakingAccount(loyaltyPool.publicKey);
+    await loyal.stakeTokens(100_000);
+
+    //one step in, claiming refreshes the working balance
+    await wait(5);
+    await loyal.claim();
+    let userObject = await program.account.user.fetch(loyal.userPubkey);
+    assert.equal(userObject.workingBalance.toNumber(), 110_000);
+
+    //doubling the stake halves the time held, dropping back below a step
+    let start = userObject.loyaltyStart.toNumber();
+    await loyal.stakeTokens(100_000);
+    userObject = await program.account.user.fetch(loyal.userPubkey);
+    assert.ok(userObject.loyaltyStart.toNumber() > start);
+    assert.equal(userObject.workingBalance.toNumber(), 200_000);
+
+    //moving out half the stake forfeits half the time held
+    start = userObject.loyaltyStart.toNumber();
+    await loyal.transferPosition(recipient.pubkey, 100_000);
+    userObject = await program.account.user.fetch(loyal.userPubkey);
+    assert.ok(userObject.loyaltyStart.toNumber() > start);
+    assert.equal(userObject.workingBalance.toNumber(), 100_000);
+  });
+
+  it('Assigns membership tiers by staked balance', async () => {
+    let tierPool = anchor.web3.Keypair.generate();
+    await funders[0].initializePool(tierPool);
+    await assert.rejects(funders[0].setTiers([1_000, 1_000, 100_000, 1_000_000], [1_000, 2_000, 3_000, 4_000]));
+    await utils.rejectsWith(
+      funders[0].setTiers([1_000, 10_000, 100_000, 1_000_000], [1_000, 2_000, 3_000, 10_001]),
+      "Tier reward b rates cannot exceed the stake each year."
+    );
+    await funders[0].setTiers([1_000, 10_000, 100_000, 1_000_000], [1_000, 2_000, 3_000, 4_000]);
+
+    let member = new User(55);
+    await member.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 200_000, mintA.publicKey, 0);
+    await member.createUserStakingAccount(tierPool.publicKey);
+
+    let tierChanges = [];
+    let listener = program.addEventListener("TierChanged", (event) => tierChanges.push(event));
+    await member.stakeTokens(50_000);
+    let userObject = await program.account.user.fetch(member.userPubkey);
+    assert.equal(userObject.tier, 2);
+
+    //lowering the thresholds moves the member up once anyone refreshes
+    await funders[0].setTiers([1_000, 10_000, 50_000, 1_000_000], [1_000, 2_000, 3_000, 4_000]);
+    await users[0].refreshTier(tierPool.publicKey, member.userPubkey);
+    userObject = await program.account.user.fetch(member.userPubkey);
+    assert.equal(userObject.tier, 3);
+
+    //views accrue without writing back, so they report no tier changes
+    await member.getPendingRewards();
+
+    await wait(1);
+    await program.removeEventListener(listener);
+    assert.equal(tierChanges.length, 2);
+    assert.deepEqual(tierChanges[0].oldTier, { none: {} });
+    assert.deepEqual(tierChanges[0].newTier, { silver: {} });
+    assert.deepEqual(tierChanges[1].oldTier, { silver: {} });
+    assert.deepEqual(tierChanges[1].newTier, { gold: {} });
+
+    //nothing funds reward b here, so claims cannot reach into the staked principal
+    await member.claim();
+    let poolObject = await program.account.pool.fetch(tierPool.publicKey);
+    let stakingVault = await provider.connection.getTokenAccountBalance(poolObject.stakingVault);
+    assert.equal(stakingVault.value.amount, "50000");
+  });
+
+  it('Pays referrers a share of their referees rewards', async () => {
+    const day = 24 * 60 * 60;
+    let referralPool = anchor.web3.Keypair.generate();
+    await funders[0].initializePool(referralPool);
+    await funders[0].setReferralConfig(1_000, 5);
+
+    let referrer = new User(56);
+    let referee = new User(57);
+    let bystander = new User(65);
+    await referrer.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 0, mintA.publicKey, 0);
+    await referee.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 100_000, mintA.publicKey, 0);
+    await bystander.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 100_000, mintA.publicKey, 0);
+    await referrer.createUserStakingAccount(referralPool.publicKey);
+    await referee.createUserStakingAccount(referralPool.publicKey, [], 0, referrer.userPubkey);
+    await bystander.createUserStakingAccount(referralPool.publicKey);
+    await referee.stakeTokens(100_000);
+    await bystander.stakeTokens(100_000);
+    await funders[0].notifyRewardAmount(8_640_000, day);
+
+    //nothing is credited until the reserve is funded
+    await wait(3);
+    await users[0].claimReferralRewards(referee.userPubkey);
+    let referrerObject = await program.account.user.fetch(referrer.userPubkey);
+    assert.equal(referrerObject.rewardA.toNumber(), 0);
+
+    await funders[0].fundReferralReserve(100);
+    await users[0].claimReferralRewards(referee.userPubkey);
+
+    let refereeObject = await program.account.user.fetch(referee.userPubkey);
+    referrerObject = await program.account.user.fetch(referrer.userPubkey);
+    let poolObject = await program.account.pool.fetch(referralPool.publicKey);
+    assert.ok(refereeObject.referrer.equals(referrer.userPubkey));
+    assert.equal(referrerObject.referralCount.toNumber(), 1);
+    //the payout cap holds the rest back
+    assert.equal(referrerObject.referralEarned.toNumber(), 5);
+    assert.equal(referrerObject.rewardA.toNumber(), 5);
+    assert.equal(poolObject.totalReferralPaid.toNumber(), 5);
+    assert.equal(poolObject.referralReserve.toNumber(), 95);
+    assert.ok(refereeObject.referralPending.toNumber() > 0);
+
+    //credits come from the reserve, the referee earns exactly what an equal staker does
+    await provider.send(new anchor.web3.Transaction().add(
+      program.instruction.refreshTier({ accounts: { pool: referralPool.publicKey, user: referee.userPubkey } }),
+      program.instruction.refreshTier({ accounts: { pool: referralPool.publicKey, user: bystander.userPubkey } }),
+    ));
+    refereeObject = await program.account.user.fetch(referee.userPubkey);
+    let bystanderObject = await program.account.user.fetch(bystander.userPubkey);
+    assert.ok(refereeObject.rewardA.toNumber() > 0);
+    assert.equal(refereeObject.rewardA.toNumber(), bystanderObject.rewardA.toNumber());
+
+    //claims never dip into what is held back for referrers
+    await referee.claim();
+    await bystander.claim();
+    await referrer.claim();
+    poolObject = await program.account.pool.fetch(referralPool.publicKey);
+    let vaultBalance = await provider.connection.getTokenAccountBalance(poolObject.rewardAVault);
+    assert.ok(parseInt(vaultBalance.value.amount) >= poolObject.referralReserve.toNumber());
+  });
+
+  it('Tracks reward A owed to stakers against campaign funding', async () => {
+    const day = 24 * 60 * 60;
+    let campaignPool = anchor.web3.Keypair.generate();
+    await funders[0].initializePool(campaignPool);
+
+    let staker = new User(66);
+    await staker.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 100_000, mintA.publicKey, 0);
+    await staker.createUserStakingAccount(campaignPool.publicKey);
+    await staker.stakeTokens(100_000);
+
+    await funders[0].notifyRewardAmount(4_320_000, day);
+    await wait(3);
+    await funders[0].notifyRewardAmount(0, day);
+    let poolObject = await program.account.pool.fetch(campaignPool.publicKey);
+    //the rolled over campaign only promises what the vault holds beyond the owed rewards
+    assert.ok(poolObject.rewardAOwed.toNumber() >= 150);
+    assert.ok(poolObject.rewardARate.toNumber() <= Math.floor((4_320_000 - poolObject.rewardAOwed.toNumber()) / day));
+
+    //paying out clears what was owed
+    await staker.claim();
+    poolObject = await program.account.pool.fetch(campaignPool.publicKey);
+    assert.ok(poolObject.totalRewardsAPaid.toNumber() >= 150);
+    assert.ok(poolObject.rewardAOwed.toNumber() <= 1);
+  });
+
+  it('Vests claimed rewards and haircuts instant claims', async () => {
+    const day = 24 * 60 * 60;
+    let vestingPool = anchor.web3.Keypair.generate();
+    await funders[0].initializePool(vestingPool);
+    await assert.rejects(funders[0].setVesting(30, 20, 0));
+    await funders[0].setVesting(3, 20, 5_000);
+
+    let vester = new User(58);
+    let instant = new User(59);
+    await vester.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 100_000, mintA.publicKey, 0);
+    await instant.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 150_000, mintA.publicKey, 0);
+    await vester.createUserStakingAccount(vestingPool.publicKey);
+    await instant.createUserStakingAccount(vestingPool.publicKey);
+    await vester.stakeTokens(100_000);
+    await instant.stakeTokens(100_000);
+    let [positionMint] = await instant.stakePosition(50_000);
+    await funders[0].notifyRewardAmount(8_640_000, day);
+
+    await wait(2);
+    //a position could never release a schedule, so it cannot claim into one
+    await utils.rejectsWith(instant.claimPosition(positionMint), "Positions cannot claim while the pool vests rewards.");
+    //the claim goes on the schedule, nothing is paid yet
+    let [amtA] = await vester.claim();
+    assert.equal(amtA, 0);
+    let userObject = await program.account.user.fetch(vester.userPubkey);
+    let vestingTotal = userObject.vestingTotal.toNumber();
+    assert.ok(vestingTotal > 0);
+    assert.equal(userObject.rewardA.toNumber(), 0);
+    assert.equal(await vester.releaseVested(), 0);
+
+    let [instantA] = await instant.claim(true);
+    assert.ok(instantA > 0);
+
+    //past the cliff part of it releases
+    await wait(4);
+    assert.ok(await vester.releaseVested() > 0);
+    userObject = await program.account.user.fetch(vester.userPubkey);
+    let released = userObject.vestingReleased.toNumber();
+    assert.ok(released > 0 && released < vestingTotal);
+
+    //a later claim does not push what is still locked back to a fresh schedule
+    let firstEnd = userObject.vestingEnd.toNumber();
+    await vester.claim();
+    userObject = await program.account.user.fetch(vester.userPubkey);
+    assert.ok(userObject.vestingEnd.toNumber() >= firstEnd);
+    assert.ok(userObject.vestingEnd.toNumber() < userObject.vestingStart.toNumber() + 20);
+  });
+
+  it('Slashes stake pro rata behind an unstake cooldown', async () => {
+    let safetyPool = anchor.web3.Keypair.generate();
+    await funders[0].initializePool(safetyPool);
+
+    let slasher = new User(60);
+    let small = new User(61);
+    let large = new User(62);
+    await slasher.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 0, mintA.publicKey, 0);
+    await small.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 100_000, mintA.publicKey, 0);
+    await large.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 300_001, mintA.publicKey, 0);
+    await small.createUserStakingAccount(safetyPool.publicKey);
+    await large.createUserStakingAccount(safetyPool.publicKey);
+    //the cooldown guards unstakes here, so the lock is cut short
+    await funders[0].setUnstakeLockPeriod(1);
+    await small.stakeTokens(100_000);
+    await large.stakeTokens(300_000);
+
+    await utils.rejectsWith(funders[0].setSlasher(slasher.pubkey, 1_000), "A slasher needs an unstake cooldown.");
+    await funders[0].setCooldown(3, 4);
+    await assert.rejects(funders[0].setSlasher(slasher.pubkey, 10_000));
+    await funders[0].setSlasher(slasher.pubkey, 1_000);
+    await utils.rejectsWith(funders[0].setCooldown(0, 0), "A slasher needs an unstake cooldown.");
+
+    //only the authority can move tokens out of the vault besides the capped slash
+    slasher.poolPubkey = safetyPool.publicKey;
+    await assert.rejects(slasher.withdraw(slasher.stakingPubkey, 1));
+
+    let slashes = [];
+    let listener = program.addEventListener("Slashed", (event) => slashes.push(event));
+    await assert.rejects(small.slash(safetyPool.publicKey, small.stakingPubkey, 40_000));
+    await assert.rejects(slasher.slash(safetyPool.publicKey, slasher.stakingPubkey, 40_001));
+    await slasher.slash(safetyPool.publicKey, slasher.stakingPubkey, 40_000);
+
+    let poolObject = await program.account.pool.fetch(safetyPool.publicKey);
+    assert.equal(poolObject.totalStaked.toNumber(), 360_000);
+    assert.equal(poolObject.totalSlashed.toNumber(), 40_000);
+    let slashed = await provider.connection.getTokenAccountBalance(slasher.stakingPubkey);
+    assert.equal(slashed.value.amount, "40000");
+
+    //stakers pick up their share of the loss on their next update
+    await small.claim();
+    await large.claim();
+    let smallObject = await program.account.user.fetch(small.userPubkey);
+    let largeObject = await program.account.user.fetch(large.userPubkey);
+    assert.equal(smallObject.balanceStaked.toNumber(), 90_000);
+    assert.equal(largeObject.balanceStaked.toNumber(), 270_000);
+
+    //nobody can leave without waiting out a cooldown first, even past the lock
+    await utils.rejectsWith(small.unstakeTokens(10_000), "Unstake cooldown has not elapsed.");
+    await small.startCooldown();
+    smallObject = await program.account.user.fetch(small.userPubkey);
+    let cooldownStart = smallObject.cooldownStart.toNumber();
+    assert.ok(cooldownStart > 0);
+    await utils.rejectsWith(small.unstakeTokens(10_000), "Unstake cooldown has not elapsed.");
+
+    //others cannot restart the cooldown with a deposit, nor move stake in that has not served one
+    await large.stakeTokensFor(small.pubkey, 1);
+    await utils.rejectsWith(
+      large.transferPosition(small.pubkey, 1),
+      "Moved stake is locked or cooling down longer than the recipient's stake."
+    );
+    smallObject = await program.account.user.fetch(small.userPubkey);
+    assert.equal(smallObject.cooldownStart.toNumber(), cooldownStart);
+    assert.equal(smallObject.balanceStaked.toNumber(), 90_001);
+
+    //inside the window the unstake goes through
+    await wait(3);
+    await small.unstakeTokens(10_000);
+    smallObject = await program.account.user.fetch(small.userPubkey);
+    assert.equal(smallObject.balanceStaked.toNumber(), 80_001);
+
+    //once the window closes a new cooldown is needed
+    await wait(5);
+    await utils.rejectsWith(small.unstakeTokens(10_000), "Unstake window has closed, start a new cooldown.");
+
+    await wait(1);
+    await program.removeEventListener(listener);
+    assert.equal(slashes.length, 1);
+    assert.equal(slashes[0].amount.toNumber(), 40_000);
+  });
+  
+  it('Hands a position over with its nft', async () => {
+    let positionPool = anchor.web3.Keypair.generate();
+    await funders[0].initializePool(positionPool);
+    await funders[0].setUnstakeLockPeriod(1);
+
+    let seller = new User(63);
+    let buyer = new User(64);
+    await seller.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 100_000, mintA.publicKey, 0);
+    await buyer.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 0, mintA.publicKey, 0);
+    seller.poolPubkey = positionPool.publicKey;
+    buyer.poolPubkey = positionPool.publicKey;
+    let [positionMint, positionPubkey, , positionMintKeypair] = await seller.stakePosition(100_000);
+
+    //only the holder or the pool authority may write the metadata
+    await utils.rejectsWith(buyer.createPositionMetadata(positionMint, "Position", "POS", "https://x/0"), "Signer does not hold the position nft.");
+    let metadata = await seller.createPositionMetadata(positionMint, "Position", "POS", "https://x/0");
+    funders[0].poolPubkey = positionPool.publicKey;
+    await funders[0].createPositionMetadata(positionMint, "Position", "POS", "https://x/1", true);
+    let metadataInfo = await provider.connection.getAccountInfo(metadata);
+    assert.ok(metadataInfo.owner.equals(utils.METADATA_PROGRAM_ID));
+    assert.ok(metadataInfo.data.includes(Buffer.from("https://x/1")));
+
+    await seller.sendPositionNft(positionMint, buyer.pubkey);
+    await wait(2);
+
+    //the stake follows the nft, the previous holder is locked out
+    await utils.rejectsWith(seller.unstakePosition(positionMint, 100_000), "Signer does not hold the position nft.");
+    await utils.rejectsWith(seller.createPositionMetadata(positionMint, "Mine", "POS", "https://x/2", true), "Signer does not hold the position nft.");
+    //signing as the mint does not make the position a wallet's user
+    await utils.rejectsWith(seller.program.rpc.startCooldown({
+      accounts: { pool: positionPool.publicKey, user: positionPubkey, owner: positionMint },
+      signers: [positionMintKeypair],
+    }), "A seeds constraint was violated");
+    await buyer.unstakePosition(positionMint, 100_000);
+    let received = await provider.connection.getTokenAccountBalance(buyer.stakingPubkey);
+    assert.equal(received.value.amount, "100000");
+
+    await utils.rejectsWith(seller.closePosition(positionMint), "Signer does not hold the position nft.");
+    await buyer.closePosition(positionMint);
+    assert.equal(await provider.connection.getAccountInfo(positionPubkey), null);
   });
   
 });
@@ -0,0 +1,165 @@
+import * as assert from "assert";
+import * as anchor from '@project-serum/anchor';
+import { TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID, Token } from "@solana/spl-token";
+import { Program } from '@project-serum/anchor';
+import { SplStaking } from '../target/types/spl_staking';
+import { StakeVault } from '../target/types/stake_vault';
+import * as utils from "./utils";
+import { User } from "./user";
+
+describe('stake-vault', () => {
+
+  anchor.setProvider(anchor.Provider.env());
+
+  const staking = anchor.workspace.SplStaking as Program<SplStaking>;
+  const program = anchor.workspace.StakeVault as Program<StakeVault>;
+  const provider = anchor.Provider.env();
+
+  let stakingMint, mintA, funder, pool, poolObject, vault;
+  let vaultSigner, userPubkey, poolSigner, tokenAccount;
+
+  it('Stakes into a pool through CPI', async () => {
+    stakingMint = await utils.createMint(provider, 9);
+    mintA = await utils.createMint(provider, 9);
+
+    funder = new User(50);
+    await funder.init(anchor.web3.LAMPORTS_PER_SOL, stakingMint.publicKey, 0, mintA.publicKey, 100_000_000_000);
+    let poolKeypair = anchor.web3.Keypair.generate();
+    await funder.initializePool(poolKeypair);
+    //short enough for the unstake test to get past it
+    await funder.setUnstakeLockPeriod(1);
+    pool = poolKeypair.publicKey;
+    poolObject = await staking.account.pool.fetch(pool);
+
+    vault = anchor.web3.Keypair.generate();
+    let nonce, userNonce;
+    [vaultSigner, nonce] = await anchor.web3.PublicKey.findProgramAddress(
+      [vault.publicKey.toBuffer()],
+      program.programId
+    );
+    [userPubkey, userNonce] = await anchor.web3.PublicKey.findProgramAddress(
+      [vaultSigner.toBuffer(), pool.toBuffer()],
+      staking.programId
+    );
+    [poolSigner] = await anchor.web3.PublicKey.findProgramAddress(
+      [pool.toBuffer()],
+      staking.programId
+    );
+
+    //the vault signer holds the tokens, the vault program signs for it
+    tokenAccount = await stakingMint.createAccount(vaultSigner);
+    await stakingMint.mintTo(tokenAccount, provider.wallet.payer, [], 100_000);
+
+    await program.rpc.initialize(nonce, userNonce, [], new anchor.BN(0), {
+      accounts: {
+        vault: vault.publicKey,
+        authority: provider.wallet.publicKey,
+        tokenAccount,
+        pool,
+        user: userPubkey,
+        payer: provider.wallet.publicKey,
+        vaultSigner,
+        stakingProgram: staking.programId,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      },
+      signers: [vault],
+      instructions: [await program.account.vault.createInstruction(vault)],
+    });
+
+    await program.rpc.stake(new anchor.BN(100_000), {
+      accounts: {
+        vault: vault.publicKey,
+        authority: provider.wallet.publicKey,
+        tokenAccount,
+        pool,
+        stakingVault: poolObject.stakingVault,
+        user: userPubkey,
+        poolSigner,
+        vaultSigner,
+        stakingProgram: staking.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      },
+    });
+
+    let userObject = await staking.account.user.fetch(userPubkey);
+    assert.ok(userObject.owner.equals(vaultSigner));
+    assert.equal(userObject.balanceStaked.toNumber(), 100_000);
+    let balance = await provider.connection.getTokenAccountBalance(tokenAccount);
+    assert.equal(balance.value.amount, "0");
+  });
+
+  it('Claims through CPI', async () => {
+    await funder.notifyRewardAmount(8_640_000, 24 * 60 * 60);
+    await wait(2);
+
+    //the vault signer is off curve and cannot pay, so its destinations are created up front
+    let rewardAAccount = await Token.getAssociatedTokenAddress(
+      ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID, mintA.publicKey, vaultSigner, true
+    );
+    let rewardBAccount = await Token.getAssociatedTokenAddress(
+      ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID, stakingMint.publicKey, vaultSigner, true
+    );
+    let tx = new anchor.web3.Transaction();
+    tx.add(Token.createAssociatedTokenAccountInstruction(
+      ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID, mintA.publicKey, rewardAAccount, vaultSigner, provider.wallet.publicKey
+    ));
+    tx.add(Token.createAssociatedTokenAccountInstruction(
+      ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID, stakingMint.publicKey, rewardBAccount, vaultSigner, provider.wallet.publicKey
+    ));
+    await provider.send(tx);
+
+    await program.rpc.claim({
+      accounts: {
+        vault: vault.publicKey,
+        authority: provider.wallet.publicKey,
+        pool,
+        stakingVault: poolObject.stakingVault,
+        rewardAVault: poolObject.rewardAVault,
+        user: userPubkey,
+        stakingMint: stakingMint.publicKey,
+        rewardAMint: mintA.publicKey,
+        rewardAAccount,
+        rewardBAccount,
+        poolSigner,
+        vaultSigner,
+        stakingProgram: staking.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      },
+    });
+
+    let rewardA = await provider.connection.getTokenAccountBalance(rewardAAccount);
+    assert.ok(Number(rewardA.value.amount) > 0);
+    let userObject = await staking.account.user.fetch(userPubkey);
+    assert.equal(userObject.rewardA.toNumber(), 0);
+  });
+
+  it('Unstakes through CPI once the lock has passed', async () => {
+    let accounts = {
+      vault: vault.publicKey,
+      authority: provider.wallet.publicKey,
+      tokenAccount,
+      pool,
+      stakingVault: poolObject.stakingVault,
+      user: userPubkey,
+      poolSigner,
+      vaultSigner,
+      stakingProgram: staking.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+    await assert.rejects(program.rpc.unstake(new anchor.BN(100_001), { accounts }));
+    await program.rpc.unstake(new anchor.BN(100_000), { accounts });
+
+    let userObject = await staking.account.user.fetch(userPubkey);
+    assert.equal(userObject.balanceStaked.toNumber(), 0);
+    let balance = await provider.connection.getTokenAccountBalance(tokenAccount);
+    assert.equal(balance.value.amount, "100000");
+  });
+
+});
+
+async function wait(seconds) {
+  await new Promise(a => setTimeout(a, seconds * 1000));
+}
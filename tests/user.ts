@@ -1,5 +1,5 @@
 import * as anchor from '@project-serum/anchor';
-import { TOKEN_PROGRAM_ID, Token } from "@solana/spl-token";
+import { TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID, Token } from "@solana/spl-token";
 import * as utils from "./utils";
 
 async function claimForUsers(users) {
@@ -21,8 +21,8 @@ async function claimForUsers(users) {
 class User {
     constructor(a) { this.id = a; }
 
-    async init(initialLamports, stakingMint, initialStaking, mintA, initialA) {
-        this.keypair = new anchor.web3.Keypair();
+    async init(initialLamports, stakingMint, initialStaking, mintA, initialA, keypair = new anchor.web3.Keypair()) {
+        this.keypair = keypair;
         this.pubkey = this.keypair.publicKey;
 
         let envProvider = anchor.Provider.env();
@@ -64,8 +64,14 @@ class User {
         let poolSigner = _poolSigner;
         let poolNonce = _nonce;
 
-        let stakingMintVault = await this.stakingMintObject.createAccount(poolSigner);
-        let mintAVault = await this.mintAObject.createAccount(poolSigner);
+        const [stakingMintVault] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("staking_vault"), poolKeypair.publicKey.toBuffer()],
+            this.program.programId
+        );
+        const [mintAVault] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("reward_a_vault"), poolKeypair.publicKey.toBuffer()],
+            this.program.programId
+        );
 
         this.poolPubkey = poolKeypair.publicKey;
         this.admin = {
@@ -87,7 +93,10 @@ class User {
                     rewardAVault: mintAVault,
                     poolSigner: poolSigner,
                     pool: this.poolPubkey,
+                    payer: this.provider.wallet.publicKey,
                     tokenProgram: TOKEN_PROGRAM_ID,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                    rent: anchor.web3.SYSVAR_RENT_PUBKEY,
                 },
                 signers: [poolKeypair],
                 instructions: [
@@ -98,7 +107,264 @@ class User {
 
     }
 
-    async createUserStakingAccount(poolPubkey) {
+    //upgradeAuthority defaults to this user, the deploying wallet has to sign for it to pass
+    async initializeRegistry(upgradeAuthority = this.keypair) {
+        const [registryPubkey, registryNonce] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("registry")],
+            this.program.programId
+        );
+        const [programData] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.program.programId.toBuffer()],
+            new anchor.web3.PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+        );
+
+        await this.program.rpc.initializeRegistry(
+            registryNonce,
+            {
+                accounts: {
+                    registry: registryPubkey,
+                    authority: this.provider.wallet.publicKey,
+                    upgradeAuthority: upgradeAuthority.publicKey,
+                    programData,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                },
+                signers: upgradeAuthority === this.keypair ? [] : [upgradeAuthority],
+            }
+        );
+
+        return registryPubkey;
+    }
+
+    async createPool(index) {
+        const [registryPubkey] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("registry")],
+            this.program.programId
+        );
+
+        let indexBuffer = Buffer.alloc(4);
+        indexBuffer.writeUInt32LE(index);
+        const [poolPubkey, poolBump] = await anchor.web3.PublicKey.findProgramAddress(
+            [
+                Buffer.from("pool"),
+                this.stakingMintObject.publicKey.toBuffer(),
+                this.mintAObject.publicKey.toBuffer(),
+                indexBuffer,
+            ],
+            this.program.programId
+        );
+
+        const [
+            _poolSigner,
+            _nonce,
+        ] = await anchor.web3.PublicKey.findProgramAddress(
+            [poolPubkey.toBuffer()],
+            this.program.programId
+        );
+        let poolSigner = _poolSigner;
+        let poolNonce = _nonce;
+
+        const [stakingMintVault] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("staking_vault"), poolPubkey.toBuffer()],
+            this.program.programId
+        );
+        const [mintAVault] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("reward_a_vault"), poolPubkey.toBuffer()],
+            this.program.programId
+        );
+
+        this.poolPubkey = poolPubkey;
+        this.admin = {
+            poolSigner,
+            poolNonce,
+            stakingMintVault,
+            mintAVault
+        };
+
+        await this.program.rpc.createPool(
+            index,
+            poolBump,
+            poolNonce,
+            {
+                accounts: {
+                    registry: registryPubkey,
+                    authority: this.provider.wallet.publicKey,
+                    stakingMint: this.stakingMintObject.publicKey,
+                    stakingVault: stakingMintVault,
+                    rewardAMint: this.mintAObject.publicKey,
+                    rewardAVault: mintAVault,
+                    poolSigner: poolSigner,
+                    pool: poolPubkey,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                    rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+                },
+            }
+        );
+
+        return poolPubkey;
+    }
+
+    async initializeNftPool(poolKeypair, nftCollection, nftRewardRate) {
+        const [
+            _poolSigner,
+            _nonce,
+        ] = await anchor.web3.PublicKey.findProgramAddress(
+            [poolKeypair.publicKey.toBuffer()],
+            this.program.programId
+        );
+        let poolSigner = _poolSigner;
+        let poolNonce = _nonce;
+
+        const [mintAVault] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("reward_a_vault"), poolKeypair.publicKey.toBuffer()],
+            this.program.programId
+        );
+
+        this.poolPubkey = poolKeypair.publicKey;
+        this.admin = {
+            poolKeypair,
+            poolSigner,
+            poolNonce,
+            mintAVault
+        };
+
+        await this.program.rpc.initializeNftPool(
+            poolNonce,
+            nftCollection,
+            new anchor.BN(nftRewardRate),
+            {
+                accounts: {
+                    authority: this.provider.wallet.publicKey,
+                    rewardAMint: this.mintAObject.publicKey,
+                    rewardAVault: mintAVault,
+                    poolSigner: poolSigner,
+                    pool: this.poolPubkey,
+                    payer: this.provider.wallet.publicKey,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                    rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+                },
+                signers: [poolKeypair],
+                instructions: [
+                    await this.program.account.pool.createInstruction(poolKeypair, ),
+                ],
+            }
+        );
+    }
+
+    async stakeNft(nftMint) {
+        const [poolSigner] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+        const [nftVault, vaultNonce] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("nft_vault"), nftMint.toBuffer()],
+            this.program.programId
+        );
+        const [stakedNft, nonce] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("staked_nft"), nftMint.toBuffer()],
+            this.program.programId
+        );
+        let nftFromAccount = await Token.getAssociatedTokenAddress(
+            ASSOCIATED_TOKEN_PROGRAM_ID,
+            TOKEN_PROGRAM_ID,
+            nftMint,
+            this.provider.wallet.publicKey,
+        );
+
+        await this.program.rpc.stakeNft(nonce, vaultNonce, {
+            accounts: {
+                pool: this.poolPubkey,
+                // User.
+                user: this.userPubkey,
+                owner: this.provider.wallet.publicKey,
+                // Nft being staked.
+                nftMint,
+                nftMetadata: await utils.metadataAddress(nftMint),
+                nftFromAccount,
+                // Escrow.
+                nftVault,
+                stakedNft,
+                // Program signers.
+                poolSigner,
+                // Misc.
+                tokenProgram: TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+                rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+            },
+        });
+        return [stakedNft, nftFromAccount];
+    }
+
+    async unstakeNft(nftMint) {
+        const [poolSigner] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+        const [nftVault] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("nft_vault"), nftMint.toBuffer()],
+            this.program.programId
+        );
+        const [stakedNft] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("staked_nft"), nftMint.toBuffer()],
+            this.program.programId
+        );
+        let nftToAccount = await Token.getAssociatedTokenAddress(
+            ASSOCIATED_TOKEN_PROGRAM_ID,
+            TOKEN_PROGRAM_ID,
+            nftMint,
+            this.provider.wallet.publicKey,
+        );
+
+        await this.program.rpc.unstakeNft({
+            accounts: {
+                pool: this.poolPubkey,
+                // User.
+                user: this.userPubkey,
+                owner: this.provider.wallet.publicKey,
+                // Escrow.
+                stakedNft,
+                nftVault,
+                nftToAccount,
+                // Program signers.
+                poolSigner,
+                // Misc.
+                tokenProgram: TOKEN_PROGRAM_ID,
+            },
+        });
+    }
+
+    async claimNftRewards() {
+        let poolObject = await this.program.account.pool.fetch(this.poolPubkey);
+        const [poolSigner] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+
+        await this.program.rpc.claimNftRewards({
+            accounts: {
+                // Stake instance.
+                pool: this.poolPubkey,
+                rewardAVault: poolObject.rewardAVault,
+                // User.
+                user: this.userPubkey,
+                owner: this.provider.wallet.publicKey,
+                rewardAMint: poolObject.rewardAMint,
+                rewardAAccount: this.mintAPubkey,
+                // Program signers.
+                poolSigner,
+                // Misc.
+                tokenProgram: TOKEN_PROGRAM_ID,
+                associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+                rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+            },
+        });
+        let amtA = await this.provider.connection.getTokenAccountBalance(this.mintAPubkey);
+        return amtA.value.uiAmount;
+    }
+
+    async createUserStakingAccount(poolPubkey, proof = [], maxStake = 0, referrer = null) {
         this.poolPubkey = poolPubkey;
 
         const [
@@ -112,13 +378,15 @@ class User {
 
         const balanceNeeded = await Token.getMinBalanceRentForExemptAccount(this.provider.connection);
 
-        await this.program.rpc.createUser(this.userNonce, {
+        await this.program.rpc.createUser(this.userNonce, proof, new anchor.BN(maxStake), {
             accounts: {
                 pool: poolPubkey,
                 user: this.userPubkey,
                 owner: this.provider.wallet.publicKey,
+                payer: this.provider.wallet.publicKey,
                 systemProgram: anchor.web3.SystemProgram.programId,
             },
+            remainingAccounts: referrer ? [{ pubkey: referrer, isWritable: true, isSigner: false }] : [],
         });
     }
 
@@ -155,6 +423,506 @@ class User {
         );
     }
 
+    //the beneficiary signs when the deposit should reset their lock and cooldown
+    async stakeTokensFor(beneficiary, amount, beneficiarySigner = null) {
+        let poolObject = await this.program.account.pool.fetch(this.poolPubkey);
+
+        const [
+            _poolSigner,
+            _nonce,
+        ] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+        let poolSigner = _poolSigner;
+
+        const [
+            beneficiaryUserPubkey, beneficiaryUserNonce,
+        ] = await anchor.web3.PublicKey.findProgramAddress(
+            [beneficiary.toBuffer(), this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+
+        await this.program.rpc.stakeFor(
+            beneficiaryUserNonce,
+            new anchor.BN(amount),
+            {
+                accounts: {
+                    // Stake instance.
+                    pool: this.poolPubkey,
+                    stakingVault: poolObject.stakingVault,
+                    // Beneficiary.
+                    user: beneficiaryUserPubkey,
+                    beneficiary,
+                    // Funding wallet.
+                    payer: this.provider.wallet.publicKey,
+                    stakeFromAccount: this.stakingPubkey,
+                    // Program signers.
+                    poolSigner,
+                    // Misc.
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                },
+                signers: beneficiarySigner ? [beneficiarySigner] : [],
+            }
+        );
+    }
+
+    async transferPosition(recipient, amount, governanceAccounts = []) {
+        const [
+            fromUserPubkey, _fromUserNonce,
+        ] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.provider.wallet.publicKey.toBuffer(), this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+
+        const [
+            toUserPubkey, toUserNonce,
+        ] = await anchor.web3.PublicKey.findProgramAddress(
+            [recipient.toBuffer(), this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+
+        await this.program.rpc.transferPosition(
+            toUserNonce,
+            new anchor.BN(amount),
+            {
+                accounts: {
+                    pool: this.poolPubkey,
+                    fromUser: fromUserPubkey,
+                    owner: this.provider.wallet.publicKey,
+                    toUser: toUserPubkey,
+                    recipient,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                },
+                remainingAccounts: governanceAccounts,
+            });
+    }
+
+    async setTransfersEnabled(enabled) {
+        await this.program.rpc.setTransfersEnabled(
+            enabled,
+            {
+                accounts: {
+                    pool: this.poolPubkey,
+                    authority: this.provider.wallet.publicKey,
+                },
+            });
+    }
+
+    async setAllowlistRoot(root) {
+        await this.program.rpc.setAllowlistRoot(
+            [...root],
+            {
+                accounts: {
+                    pool: this.poolPubkey,
+                    authority: this.provider.wallet.publicKey,
+                },
+            });
+    }
+
+    async setLoyaltySchedule(stepDuration, stepBps, maxBps) {
+        await this.program.rpc.setLoyaltySchedule(
+            new anchor.BN(stepDuration),
+            new anchor.BN(stepBps),
+            new anchor.BN(maxBps),
+            {
+                accounts: {
+                    pool: this.poolPubkey,
+                    authority: this.provider.wallet.publicKey,
+                },
+            });
+    }
+
+    async setTiers(thresholds, rewardBBps) {
+        await this.program.rpc.setTiers(
+            thresholds.map(a => new anchor.BN(a)),
+            rewardBBps.map(a => new anchor.BN(a)),
+            {
+                accounts: {
+                    pool: this.poolPubkey,
+                    authority: this.provider.wallet.publicKey,
+                },
+            });
+    }
+
+    //anyone can refresh a tier, pass the staker's pool and user
+    async refreshTier(pool, user) {
+        await this.program.rpc.refreshTier({
+            accounts: {
+                pool,
+                user,
+            },
+        });
+    }
+
+    async setReferralConfig(referralBps, referralPayoutCap) {
+        await this.program.rpc.setReferralConfig(new anchor.BN(referralBps), new anchor.BN(referralPayoutCap), {
+            accounts: {
+                pool: this.poolPubkey,
+                authority: this.provider.wallet.publicKey,
+            },
+        });
+    }
+
+    async setVesting(cliff, duration, haircutBps) {
+        await this.program.rpc.setVesting(new anchor.BN(cliff), new anchor.BN(duration), new anchor.BN(haircutBps), {
+            accounts: {
+                pool: this.poolPubkey,
+                authority: this.provider.wallet.publicKey,
+            },
+        });
+    }
+
+    async setSlasher(slasher, maxSlashBps) {
+        await this.program.rpc.setSlasher(slasher, new anchor.BN(maxSlashBps), {
+            accounts: {
+                pool: this.poolPubkey,
+                authority: this.provider.wallet.publicKey,
+            },
+        });
+    }
+
+    async setCooldown(cooldownDuration, unstakeWindow) {
+        await this.program.rpc.setCooldown(new anchor.BN(cooldownDuration), new anchor.BN(unstakeWindow), {
+            accounts: {
+                pool: this.poolPubkey,
+                authority: this.provider.wallet.publicKey,
+            },
+        });
+    }
+
+    async setUnstakeLockPeriod(unstakeLockPeriod) {
+        await this.program.rpc.setUnstakeLockPeriod(new anchor.BN(unstakeLockPeriod), {
+            accounts: {
+                pool: this.poolPubkey,
+                authority: this.provider.wallet.publicKey,
+            },
+        });
+    }
+
+    async withdraw(withdrawAccount, amount) {
+        let poolObject = await this.program.account.pool.fetch(this.poolPubkey);
+        const [poolSigner] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+
+        await this.program.rpc.withdraw(new anchor.BN(amount), {
+            accounts: {
+                pool: this.poolPubkey,
+                stakingVault: poolObject.stakingVault,
+                authority: this.provider.wallet.publicKey,
+                withdrawAccount,
+                poolSigner,
+                tokenProgram: TOKEN_PROGRAM_ID,
+            },
+        });
+    }
+
+    async startCooldown() {
+        await this.program.rpc.startCooldown({
+            accounts: {
+                pool: this.poolPubkey,
+                user: this.userPubkey,
+                owner: this.provider.wallet.publicKey,
+            },
+        });
+    }
+
+    async slash(poolPubkey, destination, amount) {
+        let poolObject = await this.program.account.pool.fetch(poolPubkey);
+        const [poolSigner] = await anchor.web3.PublicKey.findProgramAddress(
+            [poolPubkey.toBuffer()],
+            this.program.programId
+        );
+
+        await this.program.rpc.slash(new anchor.BN(amount), {
+            accounts: {
+                pool: poolPubkey,
+                stakingVault: poolObject.stakingVault,
+                slasher: this.provider.wallet.publicKey,
+                destination,
+                poolSigner,
+                tokenProgram: TOKEN_PROGRAM_ID,
+            },
+        });
+    }
+
+    async releaseVested() {
+        let poolObject = await this.program.account.pool.fetch(this.poolPubkey);
+        const [poolSigner] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+
+        await this.program.rpc.releaseVested({
+            accounts: {
+                pool: this.poolPubkey,
+                rewardAVault: poolObject.rewardAVault,
+                user: this.userPubkey,
+                owner: this.provider.wallet.publicKey,
+                rewardAAccount: this.mintAPubkey,
+                poolSigner,
+                tokenProgram: TOKEN_PROGRAM_ID,
+            },
+        });
+
+        let amtA = await this.provider.connection.getTokenAccountBalance(this.mintAPubkey);
+        return amtA.value.uiAmount;
+    }
+
+    async fundReferralReserve(amount) {
+        let poolObject = await this.program.account.pool.fetch(this.poolPubkey);
+        const [poolSigner] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+        await this.program.rpc.fundReferralReserve(new anchor.BN(amount), {
+            accounts: {
+                pool: this.poolPubkey,
+                rewardAVault: poolObject.rewardAVault,
+                funder: this.provider.wallet.publicKey,
+                fromA: this.mintAPubkey,
+                poolSigner,
+                tokenProgram: TOKEN_PROGRAM_ID,
+            },
+        });
+    }
+
+    async claimReferralRewards(referee) {
+        let refereeObject = await this.program.account.user.fetch(referee);
+        await this.program.rpc.claimReferralRewards({
+            accounts: {
+                pool: refereeObject.pool,
+                referee,
+                referrer: refereeObject.referrer,
+            },
+        });
+    }
+
+    async setDepositCaps(maxTotalStaked, maxPerUser) {
+        await this.program.rpc.setDepositCaps(
+            new anchor.BN(maxTotalStaked),
+            new anchor.BN(maxPerUser),
+            {
+                accounts: {
+                    pool: this.poolPubkey,
+                    authority: this.provider.wallet.publicKey,
+                },
+            });
+    }
+
+    async setStakeForResetsLock(resetsLock) {
+        await this.program.rpc.setStakeForResetsLock(
+            resetsLock,
+            {
+                accounts: {
+                    pool: this.poolPubkey,
+                    authority: this.provider.wallet.publicKey,
+                },
+            });
+    }
+
+    async stakePosition(amount) {
+        let poolObject = await this.program.account.pool.fetch(this.poolPubkey);
+
+        const [
+            _poolSigner,
+            _nonce,
+        ] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+        let poolSigner = _poolSigner;
+
+        let positionMint = anchor.web3.Keypair.generate();
+        const [
+            positionPubkey, positionNonce,
+        ] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("position"), positionMint.publicKey.toBuffer()],
+            this.program.programId
+        );
+        let positionTokenAccount = await Token.getAssociatedTokenAddress(
+            ASSOCIATED_TOKEN_PROGRAM_ID,
+            TOKEN_PROGRAM_ID,
+            positionMint.publicKey,
+            this.provider.wallet.publicKey,
+        );
+
+        await this.program.rpc.stakePosition(
+            positionNonce,
+            new anchor.BN(amount),
+            {
+                accounts: {
+                    // Stake instance.
+                    pool: this.poolPubkey,
+                    stakingVault: poolObject.stakingVault,
+                    // Position nft.
+                    positionMint: positionMint.publicKey,
+                    position: positionPubkey,
+                    positionTokenAccount,
+                    // Staker.
+                    owner: this.provider.wallet.publicKey,
+                    stakeFromAccount: this.stakingPubkey,
+                    // Program signers.
+                    poolSigner,
+                    // Misc.
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                    rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+                },
+                signers: [positionMint],
+            }
+        );
+
+        return [positionMint.publicKey, positionPubkey, positionTokenAccount, positionMint];
+    }
+
+    async claimPosition(positionMint) {
+        let poolObject = await this.program.account.pool.fetch(this.poolPubkey);
+
+        const [
+            _poolSigner,
+            _nonce,
+        ] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+        let poolSigner = _poolSigner;
+
+        const [positionPubkey] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("position"), positionMint.toBuffer()],
+            this.program.programId
+        );
+        let positionTokenAccount = await Token.getAssociatedTokenAddress(
+            ASSOCIATED_TOKEN_PROGRAM_ID,
+            TOKEN_PROGRAM_ID,
+            positionMint,
+            this.provider.wallet.publicKey,
+        );
+
+        await this.program.rpc.claimPosition({
+            remainingAccounts: await this.extraRewardAccounts(poolObject),
+            accounts: {
+                // Stake instance.
+                pool: this.poolPubkey,
+                stakingVault: poolObject.stakingVault,
+                rewardAVault: poolObject.rewardAVault,
+                // Position.
+                position: positionPubkey,
+                positionTokenAccount,
+                holder: this.provider.wallet.publicKey,
+                stakingMint: poolObject.stakingMint,
+                rewardAMint: poolObject.rewardAMint,
+                rewardAAccount: this.mintAPubkey,
+                rewardBAccount: this.stakingPubkey,
+                // Program signers.
+                poolSigner,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+                rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+            },
+        });
+    }
+
+    async positionAccounts(positionMint) {
+        const [positionPubkey] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("position"), positionMint.toBuffer()],
+            this.program.programId
+        );
+        let positionTokenAccount = await Token.getAssociatedTokenAddress(
+            ASSOCIATED_TOKEN_PROGRAM_ID,
+            TOKEN_PROGRAM_ID,
+            positionMint,
+            this.provider.wallet.publicKey,
+        );
+        return [positionPubkey, positionTokenAccount];
+    }
+
+    async createPositionMetadata(positionMint, name, symbol, uri, update = false) {
+        const [poolSigner] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+        let [positionPubkey, positionTokenAccount] = await this.positionAccounts(positionMint);
+        const [metadata] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("metadata"), utils.METADATA_PROGRAM_ID.toBuffer(), positionMint.toBuffer()],
+            utils.METADATA_PROGRAM_ID
+        );
+
+        let accounts = {
+            pool: this.poolPubkey,
+            position: positionPubkey,
+            positionMint,
+            positionTokenAccount,
+            metadata,
+            holder: this.provider.wallet.publicKey,
+            poolSigner,
+            metadataProgram: utils.METADATA_PROGRAM_ID,
+        };
+        if (update) {
+            await this.program.rpc.updatePositionMetadata(name, symbol, uri, { accounts });
+        } else {
+            await this.program.rpc.createPositionMetadata(name, symbol, uri, {
+                accounts: {
+                    ...accounts,
+                    payer: this.provider.wallet.publicKey,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                },
+            });
+        }
+        return metadata;
+    }
+
+    async sendPositionNft(positionMint, recipient) {
+        let [, positionTokenAccount] = await this.positionAccounts(positionMint);
+        let token = new Token(this.provider.connection, positionMint, TOKEN_PROGRAM_ID, this.keypair);
+        let recipientAccount = await token.getOrCreateAssociatedAccountInfo(recipient);
+        await token.transfer(positionTokenAccount, recipientAccount.address, this.keypair, [], 1);
+    }
+
+    //governanceAccounts are the realm and token owner record, for pools with a realm
+    async unstakePosition(positionMint, amount, governanceAccounts = []) {
+        let poolObject = await this.program.account.pool.fetch(this.poolPubkey);
+        const [poolSigner] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+        let [positionPubkey, positionTokenAccount] = await this.positionAccounts(positionMint);
+
+        await this.program.rpc.unstakePosition(new anchor.BN(amount), {
+            accounts: {
+                pool: this.poolPubkey,
+                stakingVault: poolObject.stakingVault,
+                position: positionPubkey,
+                positionTokenAccount,
+                holder: this.provider.wallet.publicKey,
+                stakeToAccount: this.stakingPubkey,
+                poolSigner,
+                tokenProgram: TOKEN_PROGRAM_ID,
+            },
+            remainingAccounts: governanceAccounts,
+        });
+    }
+
+    async closePosition(positionMint) {
+        let [positionPubkey, positionTokenAccount] = await this.positionAccounts(positionMint);
+
+        await this.program.rpc.closePosition({
+            accounts: {
+                pool: this.poolPubkey,
+                position: positionPubkey,
+                positionMint,
+                positionTokenAccount,
+                holder: this.provider.wallet.publicKey,
+                tokenProgram: TOKEN_PROGRAM_ID,
+            },
+        });
+    }
+
     async pausePool(authority) {
         let poolObject = await this.program.account.pool.fetch(this.poolPubkey);
 
@@ -203,7 +971,8 @@ class User {
         );
     }
 
-    async unstakeTokens(amount) {
+    //governanceAccounts are the realm and token owner record, for pools with a realm
+    async unstakeTokens(amount, governanceAccounts = []) {
         let poolObject = await this.program.account.pool.fetch(this.poolPubkey);
 
         const [
@@ -232,6 +1001,7 @@ class User {
                     clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
                     tokenProgram: TOKEN_PROGRAM_ID,
                 },
+                remainingAccounts: governanceAccounts,
             });
     }
 
@@ -251,32 +1021,61 @@ class User {
             oldFunder,
             {
                 accounts: {
-                    pool: this.poolPubkey,
-                    authority: this.provider.wallet.publicKey,
+                    pool: this.poolPubkey,
+                    authority: this.provider.wallet.publicKey,
+                },
+            });
+    }
+
+    async fund(amountA) {
+        let pubkeyToUse = this.poolPubkey;
+        let poolObject = await this.program.account.pool.fetch(pubkeyToUse);
+
+        const [
+            _poolSigner,
+            _nonce,
+        ] = await anchor.web3.PublicKey.findProgramAddress(
+            [pubkeyToUse.toBuffer()],
+            this.program.programId
+        );
+        let poolSigner = _poolSigner;
+
+        await this.program.rpc.fund(
+            new anchor.BN(amountA),
+            {
+                accounts: {
+                    // Stake instance.
+                    pool: pubkeyToUse,
+                    stakingVault: poolObject.stakingVault,
+                    rewardAVault: poolObject.rewardAVault,
+                    funder: this.provider.wallet.publicKey,
+                    fromA: this.mintAPubkey,
+                    // Program signers.
+                    poolSigner,
+                    tokenProgram: TOKEN_PROGRAM_ID,
                 },
             });
     }
 
-    async fund(amountA) {
-        let pubkeyToUse = this.poolPubkey;
-        let poolObject = await this.program.account.pool.fetch(pubkeyToUse);
+    async notifyRewardAmount(amountA, duration) {
+        let poolObject = await this.program.account.pool.fetch(this.poolPubkey);
 
         const [
             _poolSigner,
             _nonce,
         ] = await anchor.web3.PublicKey.findProgramAddress(
-            [pubkeyToUse.toBuffer()],
+            [this.poolPubkey.toBuffer()],
             this.program.programId
         );
         let poolSigner = _poolSigner;
 
-        await this.program.rpc.fund(
+        await this.program.rpc.notifyRewardAmount(
             new anchor.BN(amountA),
+            new anchor.BN(duration),
             {
                 accounts: {
                     // Stake instance.
-                    pool: pubkeyToUse,
-                    stakingVault: poolObject.stakingVault,
+                    pool: this.poolPubkey,
                     rewardAVault: poolObject.rewardAVault,
                     funder: this.provider.wallet.publicKey,
                     fromA: this.mintAPubkey,
@@ -333,7 +1132,73 @@ class User {
         return currentPending;
     }
 
-    async claim() {
+    async extraRewardAccounts(poolObject) {
+        //(vault, destination) pairs for every active extra reward, in pool order
+        let remainingAccounts = [];
+        for (const reward of poolObject.extraRewards) {
+            if (reward.mint.equals(anchor.web3.PublicKey.default)) {
+                continue;
+            }
+            let destination = await Token.getAssociatedTokenAddress(
+                ASSOCIATED_TOKEN_PROGRAM_ID,
+                TOKEN_PROGRAM_ID,
+                reward.mint,
+                this.provider.wallet.publicKey,
+            );
+            remainingAccounts.push({ pubkey: reward.vault, isWritable: true, isSigner: false });
+            remainingAccounts.push({ pubkey: destination, isWritable: true, isSigner: false });
+        }
+        return remainingAccounts;
+    }
+
+    async addReward(rewardMintObject) {
+        const [
+            _poolSigner,
+            _nonce,
+        ] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+        let poolSigner = _poolSigner;
+
+        const [rewardVault] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("reward_vault"), this.poolPubkey.toBuffer(), rewardMintObject.publicKey.toBuffer()],
+            this.program.programId
+        );
+
+        await this.program.rpc.addReward({
+            accounts: {
+                pool: this.poolPubkey,
+                authority: this.provider.wallet.publicKey,
+                rewardMint: rewardMintObject.publicKey,
+                rewardVault,
+                poolSigner,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+                rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+            },
+        });
+
+        return rewardVault;
+    }
+
+    async notifyExtraRewardAmount(index, rewardVault, from, amount, duration) {
+        await this.program.rpc.notifyExtraRewardAmount(
+            index,
+            new anchor.BN(amount),
+            new anchor.BN(duration),
+            {
+                accounts: {
+                    pool: this.poolPubkey,
+                    rewardVault,
+                    funder: this.provider.wallet.publicKey,
+                    from,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                },
+            });
+    }
+
+    async claim(instant = false) {
         let poolObject = await this.program.account.pool.fetch(this.poolPubkey);
 
         const [
@@ -345,7 +1210,9 @@ class User {
         );
         let poolSigner = _poolSigner;
 
-        await this.program.rpc.claim({
+        //an instant claim skips vesting at the pool's haircut
+        await this.program.rpc[instant ? "claimInstant" : "claim"]({
+            remainingAccounts: await this.extraRewardAccounts(poolObject),
             accounts: {
                 // Stake instance.
                 pool: this.poolPubkey,
@@ -354,11 +1221,16 @@ class User {
                 // User.
                 user: this.userPubkey,
                 owner: this.provider.wallet.publicKey,
+                stakingMint: poolObject.stakingMint,
+                rewardAMint: poolObject.rewardAMint,
                 rewardAAccount: this.mintAPubkey,
                 rewardBAccount: this.stakingPubkey,
                 // Program signers.
                 poolSigner,
                 tokenProgram: TOKEN_PROGRAM_ID,
+                associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+                rent: anchor.web3.SYSVAR_RENT_PUBKEY,
             },
         });
 
@@ -368,6 +1240,375 @@ class User {
         return [amtA.value.uiAmount, amtB.value.uiAmount];
     }
 
+    //simulates a view instruction and returns the borsh bytes it set as return data
+    async simulateView(ix) {
+        let tx = new anchor.web3.Transaction().add(ix);
+        tx.feePayer = this.provider.wallet.publicKey;
+        tx.recentBlockhash = (await this.provider.connection.getRecentBlockhash()).blockhash;
+        tx = await this.provider.wallet.signTransaction(tx);
+
+        let result = await this.provider.connection._rpcRequest("simulateTransaction", [
+            tx.serialize().toString("base64"),
+            { encoding: "base64", commitment: "processed" },
+        ]);
+        return Buffer.from(result.result.value.returnData.data[0], "base64");
+    }
+
+    async getPendingRewards() {
+        let data = await this.simulateView(this.program.instruction.getPendingRewards({
+            accounts: {
+                pool: this.poolPubkey,
+                user: this.userPubkey,
+            },
+        }));
+        let extraRewards = [];
+        for (let offset = 16; offset < data.length; offset += 8) {
+            extraRewards.push(new anchor.BN(data.slice(offset, offset + 8), "le"));
+        }
+        return {
+            rewardA: new anchor.BN(data.slice(0, 8), "le"),
+            rewardB: new anchor.BN(data.slice(8, 16), "le"),
+            extraRewards,
+        };
+    }
+
+    async getPoolSummary() {
+        let data = await this.simulateView(this.program.instruction.getPoolSummary({
+            accounts: {
+                pool: this.poolPubkey,
+            },
+        }));
+        let u64 = (offset) => new anchor.BN(data.slice(offset, offset + 8), "le");
+        return {
+            paused: data[0] === 1,
+            userStakeCount: data.readUInt32LE(1),
+            totalStaked: u64(5),
+            rewardARate: u64(13),
+            rewardAPerTokenStored: new anchor.BN(data.slice(21, 37), "le"),
+            periodFinish: u64(37),
+            nftRewardRate: u64(45),
+            totalRewardsAPaid: u64(53),
+            totalRewardsBPaid: u64(61),
+            totalFundedA: u64(69),
+            totalFundedB: u64(77),
+            lastActivityTime: u64(85),
+        };
+    }
+
+    async getUnlockTime() {
+        let data = await this.simulateView(this.program.instruction.getUnlockTime({
+            accounts: {
+                pool: this.poolPubkey,
+                user: this.userPubkey,
+            },
+        }));
+        return {
+            stakeTime: new anchor.BN(data.slice(0, 8), "le"),
+            unlockTime: new anchor.BN(data.slice(8, 16), "le"),
+            unlocked: data[16] === 1,
+        };
+    }
+
+    async getRemainingCapacity() {
+        let data = await this.simulateView(this.program.instruction.getRemainingCapacity({
+            accounts: {
+                pool: this.poolPubkey,
+                user: this.userPubkey,
+            },
+        }));
+        return {
+            maxTotalStaked: new anchor.BN(data.slice(0, 8), "le"),
+            maxPerUser: new anchor.BN(data.slice(8, 16), "le"),
+            remaining: new anchor.BN(data.slice(16, 24), "le"),
+        };
+    }
+
+    async createLock(amount, lockEnd) {
+        const [_escrowPubkey, _escrowNonce] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("vote_escrow"), this.poolPubkey.toBuffer(), this.pubkey.toBuffer()],
+            this.program.programId
+        );
+        const [_escrowVault, _escrowVaultNonce] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("escrow_vault"), _escrowPubkey.toBuffer()],
+            this.program.programId
+        );
+        const [poolSigner] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+        this.escrowPubkey = _escrowPubkey;
+        this.escrowVault = _escrowVault;
+
+        await this.program.rpc.createLock(_escrowNonce, _escrowVaultNonce, new anchor.BN(amount), new anchor.BN(lockEnd), {
+            accounts: {
+                pool: this.poolPubkey,
+                stakingMint: this.stakingMintObject.publicKey,
+                owner: this.provider.wallet.publicKey,
+                lockFromAccount: this.stakingPubkey,
+                voteEscrow: this.escrowPubkey,
+                escrowVault: this.escrowVault,
+                poolSigner,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+                rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+            },
+        });
+    }
+
+    async increaseLockAmount(amount) {
+        await this.program.rpc.increaseLockAmount(new anchor.BN(amount), {
+            accounts: {
+                pool: this.poolPubkey,
+                owner: this.provider.wallet.publicKey,
+                lockFromAccount: this.stakingPubkey,
+                voteEscrow: this.escrowPubkey,
+                escrowVault: this.escrowVault,
+                tokenProgram: TOKEN_PROGRAM_ID,
+            },
+        });
+    }
+
+    async extendLock(lockEnd) {
+        await this.program.rpc.extendLock(new anchor.BN(lockEnd), {
+            accounts: {
+                pool: this.poolPubkey,
+                owner: this.provider.wallet.publicKey,
+                voteEscrow: this.escrowPubkey,
+            },
+        });
+    }
+
+    async withdrawLock() {
+        const [poolSigner] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+        await this.program.rpc.withdrawLock({
+            accounts: {
+                pool: this.poolPubkey,
+                owner: this.provider.wallet.publicKey,
+                lockToAccount: this.stakingPubkey,
+                voteEscrow: this.escrowPubkey,
+                escrowVault: this.escrowVault,
+                poolSigner,
+                tokenProgram: TOKEN_PROGRAM_ID,
+            },
+        });
+    }
+
+    async getVotingPower(timestamp) {
+        let data = await this.simulateView(this.program.instruction.getVotingPower(new anchor.BN(timestamp), {
+            accounts: {
+                voteEscrow: this.escrowPubkey,
+            },
+        }));
+        return {
+            amount: new anchor.BN(data.slice(0, 8), "le"),
+            lockEnd: new anchor.BN(data.slice(8, 16), "le"),
+            votingPower: new anchor.BN(data.slice(16, 24), "le"),
+        };
+    }
+
+    async setGovernanceRealm(realm) {
+        await this.program.rpc.setGovernanceRealm(realm, {
+            accounts: {
+                pool: this.poolPubkey,
+                authority: this.provider.wallet.publicKey,
+            },
+        });
+    }
+
+    async updateVoterWeightRecord() {
+        let [ix, record] = await this.updateVoterWeightRecordInstruction();
+        await this.provider.send(new anchor.web3.Transaction().add(ix));
+        return record;
+    }
+
+    //governance only takes weights refreshed in the same slot, so this goes in front of every governance instruction
+    async updateVoterWeightRecordInstruction() {
+        const [record] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("voter_weight_record"), this.poolPubkey.toBuffer(), this.pubkey.toBuffer()],
+            this.program.programId
+        );
+        let ix = this.program.instruction.updateVoterWeightRecord({
+            accounts: {
+                pool: this.poolPubkey,
+                user: this.userPubkey,
+                voterWeightRecord: record,
+                payer: this.provider.wallet.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        return [ix, record];
+    }
+
+    async updateMaxVoterWeightRecord() {
+        const [record] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("max_voter_weight_record"), this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+        await this.program.rpc.updateMaxVoterWeightRecord({
+            accounts: {
+                pool: this.poolPubkey,
+                maxVoterWeightRecord: record,
+                payer: this.provider.wallet.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        return record;
+    }
+
+    async createGaugeController(controllerKeypair, escrowPool, emissionPerEpoch, epochDuration) {
+        const [controllerSigner, nonce] = await anchor.web3.PublicKey.findProgramAddress(
+            [controllerKeypair.publicKey.toBuffer()],
+            this.program.programId
+        );
+        const [emissionVault] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("emission_vault"), controllerKeypair.publicKey.toBuffer()],
+            this.program.programId
+        );
+        await this.program.rpc.createGaugeController(nonce, new anchor.BN(emissionPerEpoch), new anchor.BN(epochDuration), {
+            accounts: {
+                controller: controllerKeypair.publicKey,
+                authority: this.provider.wallet.publicKey,
+                escrowPool,
+                rewardAMint: this.mintAObject.publicKey,
+                emissionVault,
+                controllerSigner,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+                rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+            },
+            signers: [controllerKeypair],
+        });
+        return emissionVault;
+    }
+
+    async fundGaugeController(controller, amount) {
+        let controllerObject = await this.program.account.gaugeController.fetch(controller);
+        await this.program.rpc.fundGaugeController(new anchor.BN(amount), {
+            accounts: {
+                controller,
+                emissionVault: controllerObject.emissionVault,
+                funder: this.provider.wallet.publicKey,
+                from: this.mintAPubkey,
+                tokenProgram: TOKEN_PROGRAM_ID,
+            },
+        });
+    }
+
+    async createGauge(controller, pool) {
+        const [gauge, nonce] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("gauge"), controller.toBuffer(), pool.toBuffer()],
+            this.program.programId
+        );
+        await this.program.rpc.createGauge(nonce, {
+            accounts: {
+                controller,
+                authority: this.provider.wallet.publicKey,
+                pool,
+                gauge,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        return gauge;
+    }
+
+    async voteGauge(controller, gauge, bps) {
+        const [voter, voterNonce] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("gauge_voter"), controller.toBuffer(), this.pubkey.toBuffer()],
+            this.program.programId
+        );
+        const [gaugeVote, voteNonce] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from("gauge_vote"), gauge.toBuffer(), this.pubkey.toBuffer()],
+            this.program.programId
+        );
+        await this.program.rpc.voteGauge(voterNonce, voteNonce, bps, {
+            accounts: {
+                controller,
+                gauge,
+                owner: this.provider.wallet.publicKey,
+                voteEscrow: this.escrowPubkey,
+                voter,
+                gaugeVote,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+    }
+
+    async checkpointGauges(controller, gaugesAndPools) {
+        let controllerObject = await this.program.account.gaugeController.fetch(controller);
+        const [controllerSigner] = await anchor.web3.PublicKey.findProgramAddress(
+            [controller.toBuffer()],
+            this.program.programId
+        );
+        let remainingAccounts = [];
+        for (const [gauge, pool] of gaugesAndPools) {
+            let poolObject = await this.program.account.pool.fetch(pool);
+            remainingAccounts.push(
+                { pubkey: gauge, isWritable: true, isSigner: false },
+                { pubkey: pool, isWritable: true, isSigner: false },
+                { pubkey: poolObject.rewardAVault, isWritable: true, isSigner: false },
+            );
+        }
+        await this.program.rpc.checkpointGauges({
+            accounts: {
+                controller,
+                emissionVault: controllerObject.emissionVault,
+                controllerSigner,
+                tokenProgram: TOKEN_PROGRAM_ID,
+            },
+            remainingAccounts,
+        });
+    }
+
+    async setBoostPool(boostPool) {
+        await this.program.rpc.setBoostPool(boostPool, {
+            accounts: {
+                pool: this.poolPubkey,
+                authority: this.provider.wallet.publicKey,
+            },
+        });
+    }
+
+    //anyone can poke, pass the staker's pool, user and wallet
+    async pokeBoost(pool, user, owner) {
+        let poolObject = await this.program.account.pool.fetch(pool);
+        const [boostUser] = await anchor.web3.PublicKey.findProgramAddress(
+            [owner.toBuffer(), poolObject.boostPool.toBuffer()],
+            this.program.programId
+        );
+        await this.program.rpc.pokeBoost({
+            accounts: {
+                pool,
+                user,
+                boostPool: poolObject.boostPool,
+                boostUser,
+            },
+        });
+    }
+
+    async migratePool() {
+        await this.program.rpc.migratePool({
+            accounts: {
+                pool: this.poolPubkey,
+                payer: this.provider.wallet.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+    }
+
+    async migrateUser() {
+        await this.program.rpc.migrateUser({
+            accounts: {
+                user: this.userPubkey,
+                pool: this.poolPubkey,
+                payer: this.provider.wallet.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+    }
+
     async closeUser() {
         await this.program.rpc.closeUser(
             {
@@ -415,6 +1656,26 @@ class User {
                 },
             });
     }
+
+    async closeNftPool() {
+        let poolObject = await this.program.account.pool.fetch(this.poolPubkey);
+        const [poolSigner] = await anchor.web3.PublicKey.findProgramAddress(
+            [this.poolPubkey.toBuffer()],
+            this.program.programId
+        );
+
+        await this.program.rpc.closeNftPool({
+            accounts: {
+                authority: this.provider.wallet.publicKey,
+                refundee: this.provider.wallet.publicKey,
+                rewardARefundee: this.mintAPubkey,
+                pool: this.poolPubkey,
+                rewardAVault: poolObject.rewardAVault,
+                poolSigner,
+                tokenProgram: TOKEN_PROGRAM_ID,
+            },
+        });
+    }
 }
 
 module.exports = {
@@ -1,5 +1,7 @@
 import * as anchor from '@project-serum/anchor';
 import { TOKEN_PROGRAM_ID, Token, MintLayout } from "@solana/spl-token";
+import { keccak_256 } from "js-sha3";
+import * as assert from "assert";
 
 async function initializeProgram(program, provider, authMintPubkey) {
     const [ _configPubkey, _nonce] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("config")], program.programId);
@@ -120,7 +122,141 @@ async function sendLamports(
     await provider.send(tx);
 }
 
+//leaf of an allowlisted wallet, maxStake 0 means no per wallet cap
+function allowlistLeaf(wallet, maxStake) {
+    let amount = new anchor.BN(maxStake).toArrayLike(Buffer, "le", 8);
+    return Buffer.from(keccak_256.arrayBuffer(Buffer.concat([wallet.toBuffer(), amount])));
+}
+
+function hashPair(a, b) {
+    let pair = Buffer.compare(a, b) <= 0 ? [a, b] : [b, a];
+    return Buffer.from(keccak_256.arrayBuffer(Buffer.concat(pair)));
+}
+
+//builds the allowlist root and a proof per leaf, hashing sorted pairs like the program
+function allowlistTree(leaves) {
+    let proofs = leaves.map(() => []);
+    let positions = leaves.map((_, i) => i);
+    let level = leaves;
+    while (level.length > 1) {
+        let next = [];
+        for (let i = 0; i < level.length; i += 2) {
+            next.push(i + 1 < level.length ? hashPair(level[i], level[i + 1]) : level[i]);
+        }
+        positions.forEach((position, leaf) => {
+            let sibling = position ^ 1;
+            if (sibling < level.length) {
+                proofs[leaf].push(level[sibling]);
+            }
+            positions[leaf] = position >> 1;
+        });
+        level = next;
+    }
+    return { root: level[0], proofs };
+}
+
+//loaded into the local validator from tests/fixtures, see Anchor.toml
+const METADATA_PROGRAM_ID = new anchor.web3.PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+function borshString(value) {
+    let bytes = Buffer.from(value);
+    let len = Buffer.alloc(4);
+    len.writeUInt32LE(bytes.length);
+    return Buffer.concat([len, bytes]);
+}
+
+async function metadataAddress(mint, suffix = []) {
+    const [address] = await anchor.web3.PublicKey.findProgramAddress(
+        [Buffer.from("metadata"), METADATA_PROGRAM_ID.toBuffer(), mint.toBuffer(), ...suffix],
+        METADATA_PROGRAM_ID
+    );
+    return address;
+}
+
+//mints a 1 of 1 nft to owner, the provider wallet is its update authority.
+//a collection nft also gets a master edition, a member is verified into its collection.
+async function createNft(provider, owner, collection = null, isCollection = false) {
+    let payer = provider.wallet.publicKey;
+    let mint = await createMint(provider, 0);
+    let account = await mint.createAssociatedTokenAccount(owner);
+    await mint.mintTo(account, provider.wallet.payer, [], 1);
+
+    let metadata = await metadataAddress(mint.publicKey);
+    let collectionField = collection
+        ? Buffer.concat([Buffer.from([1, 0]), collection.toBuffer()])
+        : Buffer.from([0]);
+    let tx = new anchor.web3.Transaction();
+    tx.add(new anchor.web3.TransactionInstruction({
+        programId: METADATA_PROGRAM_ID,
+        keys: [
+            { pubkey: metadata, isSigner: false, isWritable: true },
+            { pubkey: mint.publicKey, isSigner: false, isWritable: false },
+            { pubkey: payer, isSigner: true, isWritable: false },
+            { pubkey: payer, isSigner: true, isWritable: true },
+            { pubkey: payer, isSigner: true, isWritable: false },
+            { pubkey: anchor.web3.SystemProgram.programId, isSigner: false, isWritable: false },
+        ],
+        //CreateMetadataAccountV3: data, is_mutable, no collection details
+        data: Buffer.concat([
+            Buffer.from([33]),
+            borshString("Nft"), borshString("NFT"), borshString(""),
+            Buffer.from([0, 0, 0]),
+            collectionField,
+            Buffer.from([0, 1, 0]),
+        ]),
+    }));
+    if (isCollection) {
+        let edition = await metadataAddress(mint.publicKey, [Buffer.from("edition")]);
+        tx.add(new anchor.web3.TransactionInstruction({
+            programId: METADATA_PROGRAM_ID,
+            keys: [
+                { pubkey: edition, isSigner: false, isWritable: true },
+                { pubkey: mint.publicKey, isSigner: false, isWritable: true },
+                { pubkey: payer, isSigner: true, isWritable: false },
+                { pubkey: payer, isSigner: true, isWritable: false },
+                { pubkey: payer, isSigner: true, isWritable: true },
+                { pubkey: metadata, isSigner: false, isWritable: true },
+                { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+                { pubkey: anchor.web3.SystemProgram.programId, isSigner: false, isWritable: false },
+            ],
+            //CreateMasterEditionV3 with a max supply of 0
+            data: Buffer.from([17, 1, 0, 0, 0, 0, 0, 0, 0, 0]),
+        }));
+    }
+    if (collection) {
+        tx.add(new anchor.web3.TransactionInstruction({
+            programId: METADATA_PROGRAM_ID,
+            keys: [
+                { pubkey: metadata, isSigner: false, isWritable: true },
+                { pubkey: payer, isSigner: true, isWritable: true },
+                { pubkey: payer, isSigner: true, isWritable: true },
+                { pubkey: collection, isSigner: false, isWritable: false },
+                { pubkey: await metadataAddress(collection), isSigner: false, isWritable: false },
+                { pubkey: await metadataAddress(collection, [Buffer.from("edition")]), isSigner: false, isWritable: false },
+            ],
+            //VerifyCollection
+            data: Buffer.from([18]),
+        }));
+    }
+    await provider.send(tx);
+    return mint.publicKey;
+}
+
+//rejects with the program error carrying this message, rather than any failure
+async function rejectsWith(promise, msg) {
+    await assert.rejects(promise, (err: any) => {
+        assert.equal(err.msg, msg);
+        return true;
+    });
+}
+
 module.exports = {
+    METADATA_PROGRAM_ID,
+    metadataAddress,
+    createNft,
+    rejectsWith,
+    allowlistLeaf,
+    allowlistTree,
     mintToAccount,
     createMintAndVault,
     createMintFromPriv,